@@ -2,7 +2,7 @@ use crate::lox_result::Result;
 use crate::runtime_error::RuntimeError;
 use crate::{object::Object, token::Token};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use gc::{Finalize, Gc, GcCell, Trace};
 
@@ -22,6 +22,11 @@ impl Environment {
         self.0.borrow().get(name)
     }
 
+    /// Look up a variable defined directly in this environment.
+    pub fn lookup(&self, name: &str) -> Option<Object> {
+        self.0.borrow().values.get(name).cloned()
+    }
+
     pub fn assign(&self, name: &Token, value: Object) -> Result<()> {
         self.0.borrow_mut().assign(name, value)
     }
@@ -30,6 +35,25 @@ impl Environment {
         self.0.borrow_mut().define(name, value)
     }
 
+    /// Define a variable which can't be assigned to after its definition.
+    pub fn define_const(&self, name: &str, value: Object) {
+        self.0.borrow_mut().define_const(name, value)
+    }
+
+    /// Fail if a declaration of `name` would replace a constant defined
+    /// directly in this environment. The resolver reports this for local
+    /// variables, but not for globals.
+    pub fn check_redeclaration(&self, name: &Token) -> Result<()> {
+        if self.0.borrow().constants.contains(&name.lexeme) {
+            return Err(RuntimeError::new(
+                Gc::new(name.clone()),
+                &format!("Can't redeclare constant '{}'.", name.lexeme),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
     fn ancestor(&self, distance: usize) -> Self {
         if distance == 0 {
             self.clone()
@@ -59,6 +83,7 @@ impl Environment {
 struct EnvironmentInternal {
     enclosing: Option<Environment>,
     values: HashMap<String, Object>,
+    constants: HashSet<String>,
 }
 
 impl EnvironmentInternal {
@@ -66,6 +91,7 @@ impl EnvironmentInternal {
         Self {
             enclosing,
             values: HashMap::new(),
+            constants: HashSet::new(),
         }
     }
 
@@ -93,6 +119,13 @@ impl EnvironmentInternal {
 
     fn assign(&mut self, name: &Token, value: Object) -> Result<()> {
         if let Some(v) = self.values.get_mut(&name.lexeme) {
+            if self.constants.contains(&name.lexeme) {
+                return Err(RuntimeError::new(
+                    Gc::new(name.clone()),
+                    &format!("Can't assign to constant '{}'.", name.lexeme),
+                )
+                .into());
+            }
             *v = value;
             return Ok(());
         }
@@ -113,6 +146,11 @@ impl EnvironmentInternal {
         self.values.insert(name.to_owned(), value);
     }
 
+    fn define_const(&mut self, name: &str, value: Object) {
+        self.constants.insert(name.to_owned());
+        self.values.insert(name.to_owned(), value);
+    }
+
     fn get_at(&self, name: &str, distance: usize) -> Object {
         self.values
             .get(name)
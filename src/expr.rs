@@ -14,7 +14,9 @@ crate::ast_struct!(
     paren,
     Gc<Token>,
     arguments,
-    Vec<Expr>
+    Vec<Expr>,
+    named_arguments,
+    Vec<(Gc<Token>, Expr)>
 );
 crate::ast_struct!(Expr, Get, object, Expr, name, Gc<Token>);
 crate::ast_struct!(Expr, Grouping, expression, Expr);
@@ -1,29 +1,136 @@
 use crate::environment::Environment;
 use crate::expr::{self, Expr};
+use crate::isolate;
 use crate::lox_callable::{Clock, LoxCallable};
+use crate::lox_channel;
 use crate::lox_class::LoxClass;
+use crate::lox_enum::LoxEnum;
 use crate::lox_function::LoxFunction;
+use crate::lox_iterator::LoxIterator;
+use crate::lox_list;
+use crate::lox_map;
+use crate::lox_number;
 use crate::lox_result::Result;
-use crate::lox_return::Return;
+use crate::lox_return::{Exit, Return, TailCall};
+use crate::lox_trait::LoxTrait;
+use crate::lox_type;
+use crate::native_assert;
+use crate::native_collections;
+use crate::native_format;
+use crate::native_fs;
+use crate::native_io;
+use crate::native_json;
+use crate::native_math::{self, Random};
+use crate::native_os;
+use crate::native_string;
+use crate::native_time;
 use crate::object::Object::{
-    self, Boolean as OBoolean, Callable as OCallable, Class as OClass,
-    Instance as OInstance, Nil as ONil, Number as ONumber, String as OString,
+    self, Boolean as OBoolean, Callable as OCallable, Channel as OChannel, Class as OClass,
+    Enum as OEnum, EnumValue as OEnumValue, Generator as OGenerator, Instance as OInstance,
+    Integer as OInteger, Isolate as OIsolate, List as OList, Map as OMap, Nil as ONil,
+    Number as ONumber, String as OString, Trait as OTrait, Type as OType,
 };
+use crate::object::{compare_numbers, with_number_format};
+use crate::reflection;
 use crate::runtime_error::RuntimeError;
-use crate::stmt::{self, Stmt};
+use crate::scheduler::{self, Scheduler};
+use crate::stmt::{self, MatchArm, Pattern, Stmt};
 use crate::token::Token;
 use crate::token_type::TokenType as TT;
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::io::Write;
+use std::error::Error;
+use std::io::{self, BufRead, Read, Write};
+use std::mem;
+use std::slice;
+use std::sync::{Arc, Mutex};
 
-use gc::{Finalize, Gc, GcCell, Trace};
+use gc::{Finalize, Gc, Trace};
 
+/// Where `print` writes to. Isolates share the output of the interpreter
+/// which started them, so a buffer can be written to from several threads.
 #[derive(Clone, Finalize, Trace)]
 pub enum InterpreterOutput {
     StdOut,
     #[allow(unused)]
-    ByteVec(Gc<GcCell<Vec<u8>>>),
+    ByteVec(#[unsafe_ignore_trace] Arc<Mutex<Vec<u8>>>),
+}
+
+/// Where `readLine` and `readAll` read from. Like the output, it's shared with
+/// isolates. Tests give scripts their input as bytes.
+#[derive(Clone)]
+pub enum InterpreterInput {
+    StdIn,
+    #[cfg(test)]
+    Bytes(Arc<Mutex<io::Cursor<Vec<u8>>>>),
+}
+
+impl InterpreterInput {
+    /// Input which reads `bytes`.
+    #[cfg(test)]
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        InterpreterInput::Bytes(Arc::new(Mutex::new(io::Cursor::new(bytes))))
+    }
+
+    /// Read a line without its line ending, or `None` at the end of the input.
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        let count = match self {
+            InterpreterInput::StdIn => io::stdin().lock().read_line(&mut line)?,
+            #[cfg(test)]
+            InterpreterInput::Bytes(bytes) => bytes.lock().unwrap().read_line(&mut line)?,
+        };
+        if count == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    /// Read the rest of the input.
+    pub fn read_all(&mut self) -> io::Result<String> {
+        let mut text = String::new();
+        match self {
+            InterpreterInput::StdIn => io::stdin().lock().read_to_string(&mut text)?,
+            #[cfg(test)]
+            InterpreterInput::Bytes(bytes) => {
+                bytes.lock().unwrap().read_to_string(&mut text)?
+            }
+        };
+        Ok(text)
+    }
+}
+
+/// Settings for an interpreter, which scripts can't change. Isolates inherit
+/// the options of the interpreter which started them.
+#[derive(Clone, Debug)]
+pub struct InterpreterOptions {
+    /// Display floats exactly as the Java reference implementation prints
+    /// them, e.g. `1.0E21` rather than `1000000000000000000000.0`, wherever
+    /// they're turned into text, except in JSON.
+    pub java_number_format: bool,
+    /// Whether scripts can use the file system natives, and start isolates,
+    /// which load their script from a file. Embedders running untrusted
+    /// scripts can turn this off.
+    pub file_access: bool,
+    /// The arguments passed to the script, which `args()` returns.
+    pub args: Vec<String>,
+}
+
+impl Default for InterpreterOptions {
+    fn default() -> Self {
+        Self {
+            java_number_format: false,
+            file_access: true,
+            args: Vec::new(),
+        }
+    }
 }
 
 pub struct Interpreter {
@@ -31,52 +138,142 @@ pub struct Interpreter {
     locals: HashMap<usize, usize>,
     environment: Environment,
     output: InterpreterOutput,
+    input: InterpreterInput,
+    scheduler: Scheduler,
+    options: InterpreterOptions,
+    random: Random,
 }
 
 impl Interpreter {
+    #[allow(unused)]
     pub fn new(output: InterpreterOutput) -> Self {
+        Self::with_options(output, InterpreterOptions::default())
+    }
+
+    pub fn with_options(output: InterpreterOutput, options: InterpreterOptions) -> Self {
         let globals = Environment::new(None);
 
         globals.define("clock", OCallable(LoxCallable::Clock(Clock::new())));
+        native_assert::define_globals(&globals);
+        native_collections::define_globals(&globals);
+        native_format::define_globals(&globals);
+        native_fs::define_globals(&globals);
+        native_io::define_globals(&globals);
+        native_json::define_globals(&globals);
+        native_math::define_globals(&globals);
+        native_os::define_globals(&globals);
+        native_string::define_globals(&globals);
+        native_time::define_globals(&globals);
+        lox_list::define_globals(&globals);
+        lox_map::define_globals(&globals);
+        lox_number::define_globals(&globals);
+        lox_channel::define_globals(&globals);
+        scheduler::define_globals(&globals);
+        isolate::define_globals(&globals);
+        reflection::define_globals(&globals);
+        lox_type::define_globals(&globals);
 
         Self {
             globals: globals.clone(),
             locals: HashMap::new(),
             environment: globals,
             output,
+            input: InterpreterInput::StdIn,
+            scheduler: Scheduler::default(),
+            options,
+            random: Random::from_time(),
         }
     }
 
-    pub fn interpret<F>(&mut self, statements: &[Stmt], mut error_handler: F)
+    /// Execute `statements`, reporting a runtime error to `error_handler`.
+    /// Returns the script's request to exit, if it called `exit`.
+    pub fn interpret<F>(&mut self, statements: &[Stmt], mut error_handler: F) -> Option<Exit>
     where
         F: FnMut(&RuntimeError),
     {
-        for statement in statements {
-            match self.execute(statement) {
-                Ok(_) => {}
+        let result = with_number_format(self.options.java_number_format, || {
+            statements
+                .iter()
+                .try_for_each(|statement| self.execute(statement))
+                .and_then(|_| scheduler::run_remaining_fibers(self))
+        });
+
+        // Drop any fibers left blocked by a deadlock or an error, so none
+        // outlive this call.
+        self.scheduler = Scheduler::default();
+
+        match result {
+            Ok(()) => None,
+            Err(error) => match error.downcast::<Exit>() {
+                Ok(exit) => Some(*exit),
                 Err(error) => {
                     (error_handler)(
                         error
                             .downcast_ref::<RuntimeError>()
                             .expect("Unexpected error"),
                     );
-                    return;
+                    None
                 }
-            }
+            },
         }
     }
 
+    pub fn with_input(mut self, input: InterpreterInput) -> Self {
+        self.input = input;
+        self
+    }
+
+    pub fn input(&mut self) -> &mut InterpreterInput {
+        &mut self.input
+    }
+
+    pub fn output(&self) -> &InterpreterOutput {
+        &self.output
+    }
+
+    pub fn options(&self) -> &InterpreterOptions {
+        &self.options
+    }
+
+    /// The generator used by `random`, which `seedRandom` reseeds.
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    pub fn scheduler(&mut self) -> &mut Scheduler {
+        &mut self.scheduler
+    }
+
+    pub fn globals(&self) -> &Environment {
+        &self.globals
+    }
+
+    /// Look up a global variable.
+    pub fn global(&self, name: &str) -> Option<Object> {
+        self.globals.lookup(name)
+    }
+
+    /// The current environment.
+    pub fn environment(&self) -> Environment {
+        self.environment.clone()
+    }
+
     fn execute(&mut self, stmt: &Stmt) -> Result<()> {
         match &stmt {
             Stmt::Block(s) => self.visit_block_stmt(s),
             Stmt::Class(s) => self.visit_class_stmt(s),
+            Stmt::Enum(s) => self.visit_enum_stmt(s),
             Stmt::Expression(s) => self.visit_expression_stmt(s),
+            Stmt::ForIn(s) => self.visit_for_in_stmt(s),
             Stmt::Function(s) => self.visit_function_stmt(s),
             Stmt::If(s) => self.visit_if_stmt(s),
+            Stmt::Match(s) => self.visit_match_stmt(s),
             Stmt::Print(s) => self.visit_print_stmt(s),
             Stmt::Return(s) => self.visit_return_stmt(s),
+            Stmt::Trait(s) => self.visit_trait_stmt(s),
             Stmt::Var(s) => self.visit_var_stmt(s),
             Stmt::While(s) => self.visit_while_stmt(s),
+            Stmt::Yield(_) => unreachable!("Yield statements are run by generators."),
         }
     }
 
@@ -104,6 +301,26 @@ impl Interpreter {
         Ok(())
     }
 
+    /// Evaluate `expr` with `environment` as the current environment.
+    pub fn evaluate_in_environment(
+        &mut self,
+        expr: &Expr,
+        environment: Environment,
+    ) -> Result<Object> {
+        self.in_environment(environment, |interpreter| interpreter.evaluate(expr))
+    }
+
+    /// Run `f` with `environment` as the current environment.
+    pub fn in_environment<T, F>(&mut self, environment: Environment, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let previous = mem::replace(&mut self.environment, environment);
+        let result = f(self);
+        self.environment = previous;
+        result
+    }
+
     fn visit_block_stmt(&mut self, stmt: &stmt::Block) -> Result<()> {
         self.execute_block(
             &stmt.statements,
@@ -113,6 +330,7 @@ impl Interpreter {
     }
 
     fn visit_class_stmt(&mut self, stmt: &stmt::Class) -> Result<()> {
+        self.environment.check_redeclaration(&stmt.name)?;
         let superclass = if let Some(superclass) = &stmt.superclass {
             if let OClass(ref c) = self.evaluate(&Expr::Variable(superclass.clone()))? {
                 Some(c.clone())
@@ -127,6 +345,19 @@ impl Interpreter {
             None
         };
 
+        let mut traits = Vec::new();
+        for trait_ in &stmt.traits {
+            if let OTrait(ref t) = self.evaluate(&Expr::Variable(trait_.clone()))? {
+                traits.push(t.clone());
+            } else {
+                return Err(RuntimeError::new(
+                    trait_.name.clone(),
+                    "Can only mix in traits.",
+                )
+                .into());
+            }
+        }
+
         self.environment.define(&stmt.name.lexeme, ONil);
 
         if stmt.superclass.is_some() {
@@ -141,7 +372,32 @@ impl Interpreter {
             );
         }
 
+        // A class's own methods take precedence over those of its traits, which
+        // in turn take precedence over those it inherits.
         let mut methods = HashMap::new();
+        let mut providers: HashMap<&str, &LoxTrait> = HashMap::new();
+        for trait_ in &traits {
+            let mut names: Vec<_> = trait_.methods().keys().collect();
+            names.sort();
+            for name in names {
+                if stmt.methods.iter().any(|m| &m.name.lexeme == name) {
+                    continue;
+                }
+                if let Some(other) = providers.insert(name, trait_) {
+                    return Err(RuntimeError::new(
+                        stmt.name.clone(),
+                        &format!(
+                            "Method '{name}' is provided by both '{}' and '{}'.",
+                            other.name(),
+                            trait_.name()
+                        ),
+                    )
+                    .into());
+                }
+                methods.insert(name.clone(), trait_.methods()[name].clone());
+            }
+        }
+
         for method in &stmt.methods {
             let function = LoxFunction::new(
                 method.clone(),
@@ -181,12 +437,34 @@ impl Interpreter {
         }
     }
 
+    fn visit_enum_stmt(&mut self, stmt: &stmt::Enum) -> Result<()> {
+        self.environment.check_redeclaration(&stmt.name)?;
+        let variants: Vec<&str> = stmt.variants.iter().map(|v| v.lexeme.as_str()).collect();
+        self.environment.define(
+            &stmt.name.lexeme,
+            OEnum(LoxEnum::new(&stmt.name.lexeme, &variants)),
+        );
+        Ok(())
+    }
+
     fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) -> Result<()> {
         self.evaluate(&stmt.expression)?;
         Ok(())
     }
 
+    fn visit_for_in_stmt(&mut self, stmt: &stmt::ForIn) -> Result<()> {
+        let iterable = self.evaluate(&stmt.iterable)?;
+        let mut iterator = LoxIterator::new(&iterable, &stmt.keyword)?;
+        while let Some(value) = iterator.next(self, &stmt.keyword)? {
+            let environment = Environment::new(Some(self.environment.clone()));
+            environment.define(&stmt.name.lexeme, value);
+            self.execute_block(slice::from_ref(&stmt.body), environment)?;
+        }
+        Ok(())
+    }
+
     fn visit_function_stmt(&mut self, stmt: &Gc<stmt::Function>) -> Result<()> {
+        self.environment.check_redeclaration(&stmt.name)?;
         let function = LoxCallable::Function(LoxFunction::new(
             stmt.clone(),
             self.environment.clone(),
@@ -206,10 +484,101 @@ impl Interpreter {
         Ok(())
     }
 
+    fn visit_match_stmt(&mut self, stmt: &stmt::Match) -> Result<()> {
+        let (arm, environment) = self.select_match_arm(stmt)?;
+        self.execute_block(slice::from_ref(&arm.body), environment)
+    }
+
+    /// Evaluate the value of a `match` statement and find the first arm which
+    /// matches it, along with the environment holding the arm's bindings.
+    pub fn select_match_arm<'s>(
+        &mut self,
+        stmt: &'s stmt::Match,
+    ) -> Result<(&'s MatchArm, Environment)> {
+        let value = self.evaluate(&stmt.value)?;
+
+        for arm in &stmt.arms {
+            let environment = Environment::new(Some(self.environment.clone()));
+            if !self.match_pattern(&arm.pattern, &value, &environment)? {
+                continue;
+            }
+            if let Some(guard) = &arm.guard {
+                if !is_truthy(&self.evaluate_in_environment(guard, environment.clone())?) {
+                    continue;
+                }
+            }
+            return Ok((arm, environment));
+        }
+
+        Err(RuntimeError::new(
+            stmt.keyword.clone(),
+            &format!("No match arm matched the value '{value}'."),
+        )
+        .into())
+    }
+
+    /// Match `value` against `pattern`, defining any variables the pattern binds
+    /// in `environment`, which is the environment of the pattern's arm.
+    fn match_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &Object,
+        environment: &Environment,
+    ) -> Result<bool> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Literal(literal) => Ok(is_equal(literal, value)),
+            Pattern::Binding(name) => {
+                environment.define(&name.lexeme, value.clone());
+                Ok(true)
+            }
+            Pattern::Class(class, fields) => {
+                let class = match self.evaluate_in_environment(
+                    &Expr::Variable(class.clone()),
+                    environment.clone(),
+                )? {
+                    OClass(ref c) => c.clone(),
+                    _ => {
+                        return Err(RuntimeError::new(
+                            class.name.clone(),
+                            "Class pattern must name a class.",
+                        )
+                        .into())
+                    }
+                };
+
+                let instance = match value {
+                    OInstance(instance) if instance.class().is_subclass_of(&class) => {
+                        instance
+                    }
+                    _ => return Ok(false),
+                };
+
+                for (name, pattern) in fields {
+                    match instance.field(&name.lexeme) {
+                        Some(field) => {
+                            if !self.match_pattern(pattern, &field, environment)? {
+                                return Ok(false);
+                            }
+                        }
+                        None => return Ok(false),
+                    }
+                }
+                Ok(true)
+            }
+            Pattern::Value(expected) => {
+                let expected =
+                    self.evaluate_in_environment(expected, environment.clone())?;
+                Ok(is_equal(&expected, value))
+            }
+        }
+    }
+
     fn visit_print_stmt(&mut self, stmt: &stmt::Print) -> Result<()> {
         let value = self.evaluate(&stmt.expression)?;
+        let value = self.stringify(&stmt.keyword, &value)?;
         match &self.output {
-            InterpreterOutput::ByteVec(v) => writeln!(v.borrow_mut(), "{value}")?,
+            InterpreterOutput::ByteVec(v) => writeln!(v.lock().unwrap(), "{value}")?,
             InterpreterOutput::StdOut => println!("{value}"),
         }
         Ok(())
@@ -217,6 +586,9 @@ impl Interpreter {
 
     fn visit_return_stmt(&mut self, stmt: &stmt::Return) -> Result<()> {
         let value = match &stmt.value {
+            Some(Expr::Call(call)) => {
+                return Err(self.evaluate_call(call)?.into());
+            }
             Some(expr) => self.evaluate(expr)?,
             None => ONil,
         };
@@ -224,14 +596,34 @@ impl Interpreter {
         Err(Return::new(value).into())
     }
 
+    fn visit_trait_stmt(&mut self, stmt: &stmt::Trait) -> Result<()> {
+        self.environment.check_redeclaration(&stmt.name)?;
+        let mut methods = HashMap::new();
+        for method in &stmt.methods {
+            let function = LoxFunction::new(method.clone(), self.environment.clone(), false);
+            methods.insert(method.name.lexeme.clone(), function);
+        }
+
+        self.environment.define(
+            &stmt.name.lexeme,
+            OTrait(LoxTrait::new(&stmt.name.lexeme, methods)),
+        );
+        Ok(())
+    }
+
     fn visit_var_stmt(&mut self, stmt: &stmt::Var) -> Result<()> {
+        self.environment.check_redeclaration(&stmt.name)?;
         let value = if let Some(initializer) = &stmt.initializer {
             self.evaluate(initializer)?
         } else {
             ONil
         };
 
-        self.environment.define(&stmt.name.lexeme, value);
+        if stmt.constant {
+            self.environment.define_const(&stmt.name.lexeme, value);
+        } else {
+            self.environment.define(&stmt.name.lexeme, value);
+        }
         Ok(())
     }
 
@@ -259,32 +651,43 @@ impl Interpreter {
         let left = self.evaluate(&expr.left)?;
         let right = self.evaluate(&expr.right)?;
 
+        if let Some(name) = binary_operator_method(&expr.operator.type_) {
+            let result = self.call_special_method(
+                &expr.operator,
+                &left,
+                name,
+                slice::from_ref(&right),
+            )?;
+            if let Some(result) = result {
+                return Ok(result);
+            }
+        }
+
         let obj = match expr.operator.type_ {
-            TT::BangEqual => OBoolean(!is_equal(&left, &right)),
-            TT::EqualEqual => OBoolean(is_equal(&left, &right)),
+            TT::BangEqual => OBoolean(!self.equals(&expr.operator, &left, &right)?),
+            TT::Is => OBoolean(is_instance_of(&expr.operator, &left, &right)?),
+            TT::EqualEqual => OBoolean(self.equals(&expr.operator, &left, &right)?),
             TT::Greater => {
-                let (l, r) = check_number_operands(&expr.operator, &left, &right)?;
-                OBoolean(l > r)
+                let ordering = compare_operands(&expr.operator, &left, &right)?;
+                OBoolean(ordering.is_some_and(Ordering::is_gt))
             }
             TT::GreaterEqual => {
-                let (l, r) = check_number_operands(&expr.operator, &left, &right)?;
-                OBoolean(l >= r)
+                let ordering = compare_operands(&expr.operator, &left, &right)?;
+                OBoolean(ordering.is_some_and(Ordering::is_ge))
             }
             TT::Less => {
-                let (l, r) = check_number_operands(&expr.operator, &left, &right)?;
-                OBoolean(l < r)
+                let ordering = compare_operands(&expr.operator, &left, &right)?;
+                OBoolean(ordering.is_some_and(Ordering::is_lt))
             }
             TT::LessEqual => {
-                let (l, r) = check_number_operands(&expr.operator, &left, &right)?;
-                OBoolean(l <= r)
-            }
-            TT::Minus => {
-                let (l, r) = check_number_operands(&expr.operator, &left, &right)?;
-                ONumber(l - r)
+                let ordering = compare_operands(&expr.operator, &left, &right)?;
+                OBoolean(ordering.is_some_and(Ordering::is_le))
             }
-            TT::Plus => match (left, right) {
-                (ONumber(l), ONumber(r)) => ONumber(l + r),
-                (OString(ref l), OString(ref r)) => OString(Gc::new((**l).clone() + &**r)),
+            TT::Plus => match (&left, &right) {
+                (OString(l), OString(r)) => OString(Gc::new((**l).clone() + &**r)),
+                _ if left.is_number() && right.is_number() => {
+                    arithmetic(&expr.operator, &left, &right)?
+                }
                 _ => {
                     return Err(RuntimeError::new(
                         expr.operator.clone(),
@@ -293,20 +696,24 @@ impl Interpreter {
                     .into())
                 }
             },
-            TT::Slash => {
-                let (l, r) = check_number_operands(&expr.operator, &left, &right)?;
-                ONumber(l / r)
-            }
-            TT::Star => {
-                let (l, r) = check_number_operands(&expr.operator, &left, &right)?;
-                ONumber(l * r)
-            }
+            TT::Minus | TT::Slash | TT::Star => arithmetic(&expr.operator, &left, &right)?,
             _ => unreachable!(),
         };
         Ok(obj)
     }
 
     fn visit_call_expr(&mut self, expr: &expr::Call) -> Result<Object> {
+        let tail_call = self.evaluate_call(expr)?;
+        tail_call.callee.call(
+            self,
+            &expr.paren,
+            &tail_call.arguments,
+            &tail_call.named_arguments,
+        )
+    }
+
+    /// Evaluate the callee and arguments of a call without making it.
+    fn evaluate_call(&mut self, expr: &expr::Call) -> Result<TailCall> {
         let callee = {
             let callee = self.evaluate(&expr.callee)?;
 
@@ -325,20 +732,19 @@ impl Interpreter {
             .map(|arg| self.evaluate(arg))
             .collect::<Result<Vec<_>>>()?;
 
+        let named_arguments = expr
+            .named_arguments
+            .iter()
+            .map(|(name, arg)| Ok((name.clone(), self.evaluate(arg)?)))
+            .collect::<Result<Vec<_>>>()?;
+
         if let OCallable(function) = &callee {
-            if arguments.len() != function.arity() {
-                Err(RuntimeError::new(
-                    expr.paren.clone(),
-                    &format!(
-                        "Expected {} arguments but got {}.",
-                        function.arity(),
-                        arguments.len()
-                    ),
-                )
-                .into())
-            } else {
-                Ok(function.call(self, &arguments)?)
-            }
+            Ok(TailCall {
+                callee: function.clone(),
+                paren: expr.paren.clone(),
+                arguments,
+                named_arguments,
+            })
         } else {
             Err(RuntimeError::new(
                 expr.paren.clone(),
@@ -350,8 +756,16 @@ impl Interpreter {
 
     fn visit_get_expr(&mut self, expr: &expr::Get) -> Result<Object> {
         let object = self.evaluate(&expr.object)?;
-        if let OInstance(instance) = &object {
-            return instance.get(&expr.name);
+        match &object {
+            OInstance(instance) => return instance.get(&expr.name),
+            OList(list) => return list.get(&expr.name),
+            OMap(map) => return map.get(&expr.name),
+            OEnum(lox_enum) => return lox_enum.get(&expr.name),
+            OEnumValue(value) => return value.get(&expr.name),
+            OGenerator(generator) => return generator.get(&expr.name),
+            OChannel(channel) => return channel.get(&expr.name),
+            OIsolate(isolate) => return isolate.get(&expr.name),
+            _ => {}
         }
         Err(RuntimeError::new(expr.name.clone(), "Only instances have properties.").into())
     }
@@ -443,8 +857,18 @@ impl Interpreter {
         match expr.operator.type_ {
             TT::Bang => Ok(OBoolean(!is_truthy(&right))),
             TT::Minus => {
-                let r = check_number_operand(&expr.operator, &right)?;
-                Ok(ONumber(-r))
+                if let Some(result) =
+                    self.call_special_method(&expr.operator, &right, "neg", &[])?
+                {
+                    return Ok(result);
+                }
+                match right {
+                    OInteger(r) => r
+                        .checked_neg()
+                        .map(OInteger)
+                        .ok_or_else(|| integer_overflow(&expr.operator)),
+                    _ => Ok(ONumber(-check_number_operand(&expr.operator, &right)?)),
+                }
             }
             _ => unreachable!(),
         }
@@ -461,13 +885,94 @@ impl Interpreter {
             self.globals.get(name)
         }
     }
+
+    /// Call the special method `name` on `receiver` if `receiver` is an instance
+    /// whose class defines it. Returns `None` if there's no such method, in which
+    /// case the caller should fall back to the built-in behaviour.
+    fn call_special_method(
+        &mut self,
+        token: &Gc<Token>,
+        receiver: &Object,
+        name: &str,
+        arguments: &[Object],
+    ) -> Result<Option<Object>> {
+        let method = match receiver {
+            OInstance(instance) => instance.find_method(name),
+            _ => None,
+        };
+
+        if let Some(method) = method {
+            if !method.arity().accepts(arguments.len()) {
+                return Err(RuntimeError::new(
+                    token.clone(),
+                    &format!(
+                        "Expected '{name}' to take {} arguments but it takes {}.",
+                        arguments.len(),
+                        method.arity().min
+                    ),
+                )
+                .into());
+            }
+            Ok(Some(method.call(self, token, arguments, &[])?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Whether `left == right` in Lox. Two instances are compared with
+    /// `left`'s `equals` method if it has one, so `equals` is only ever passed
+    /// an instance, and an instance is never equal to a value of another type.
+    pub fn equals(
+        &mut self,
+        token: &Gc<Token>,
+        left: &Object,
+        right: &Object,
+    ) -> Result<bool> {
+        if let (OInstance(_), OInstance(_)) = (left, right) {
+            let arguments = slice::from_ref(right);
+            if let Some(result) =
+                self.call_special_method(token, left, "equals", arguments)?
+            {
+                return Ok(is_truthy(&result));
+            }
+        }
+        Ok(is_equal(left, right))
+    }
+
+    /// `value` as `print` displays it, using its `toString` method if it has
+    /// one.
+    pub fn stringify(&mut self, token: &Gc<Token>, value: &Object) -> Result<String> {
+        if let Some(string) = self.call_special_method(token, value, "toString", &[])? {
+            return Ok(string.to_string());
+        }
+        Ok(value.to_string())
+    }
+}
+
+/// The special method a class can define to overload a binary operator. Only
+/// the left operand's method is called, so `1 + v` is an error even if `v`'s
+/// class defines `add`. Equality is handled by `Interpreter::equals`.
+fn binary_operator_method(type_: &TT) -> Option<&'static str> {
+    match type_ {
+        TT::Greater => Some("gt"),
+        TT::GreaterEqual => Some("ge"),
+        TT::Less => Some("lt"),
+        TT::LessEqual => Some("le"),
+        TT::Minus => Some("sub"),
+        TT::Plus => Some("add"),
+        TT::Slash => Some("div"),
+        TT::Star => Some("mul"),
+        _ => None,
+    }
 }
 
 fn check_number_operand(operator: &Token, operand: &Object) -> Result<f64> {
-    if let ONumber(l) = operand {
-        Ok(*l)
-    } else {
-        Err(RuntimeError::new(Gc::new(operator.clone()), "Operand must be a number.").into())
+    match operand {
+        ONumber(l) => Ok(*l),
+        OInteger(l) => Ok(*l as f64),
+        _ => Err(
+            RuntimeError::new(Gc::new(operator.clone()), "Operand must be a number.").into(),
+        ),
     }
 }
 
@@ -476,14 +981,91 @@ fn check_number_operands(
     left: &Object,
     right: &Object,
 ) -> Result<(f64, f64)> {
-    if let (ONumber(l), ONumber(r)) = (left, right) {
-        Ok((*l, *r))
-    } else {
-        Err(RuntimeError::new(Gc::new(operator.clone()), "Operands must be numbers.").into())
+    match (left, right) {
+        (ONumber(_) | OInteger(_), ONumber(_) | OInteger(_)) => Ok((
+            check_number_operand(operator, left)?,
+            check_number_operand(operator, right)?,
+        )),
+        _ => Err(
+            RuntimeError::new(Gc::new(operator.clone()), "Operands must be numbers.").into(),
+        ),
+    }
+}
+
+/// Compare two numbers, or two strings lexicographically, where `None` means
+/// one of the numbers is NaN.
+fn compare_operands(
+    operator: &Token,
+    left: &Object,
+    right: &Object,
+) -> Result<Option<Ordering>> {
+    if let (OString(l), OString(r)) = (left, right) {
+        return Ok(Some(l.cmp(r)));
+    }
+    if !left.is_number() || !right.is_number() {
+        return Err(RuntimeError::new(
+            Gc::new(operator.clone()),
+            "Operands must be two numbers or two strings.",
+        )
+        .into());
+    }
+    Ok(compare_numbers(left, right))
+}
+
+/// Apply an arithmetic operator. Integers stay integers, raising an error if
+/// they overflow, except that dividing them gives a float unless the division
+/// is exact. Any other numbers are promoted to floats.
+fn arithmetic(operator: &Gc<Token>, left: &Object, right: &Object) -> Result<Object> {
+    if let (OInteger(l), OInteger(r)) = (left, right) {
+        let result = match operator.type_ {
+            TT::Minus => l.checked_sub(*r),
+            TT::Plus => l.checked_add(*r),
+            TT::Star => l.checked_mul(*r),
+            TT::Slash => {
+                return Ok(match l.checked_rem(*r) {
+                    Some(0) => OInteger(l / r),
+                    _ => ONumber(*l as f64 / *r as f64),
+                })
+            }
+            _ => unreachable!(),
+        };
+        return result
+            .map(OInteger)
+            .ok_or_else(|| integer_overflow(operator));
+    }
+
+    let (l, r) = check_number_operands(operator, left, right)?;
+    Ok(ONumber(match operator.type_ {
+        TT::Minus => l - r,
+        TT::Plus => l + r,
+        TT::Slash => l / r,
+        TT::Star => l * r,
+        _ => unreachable!(),
+    }))
+}
+
+fn integer_overflow(operator: &Gc<Token>) -> Box<dyn Error> {
+    RuntimeError::new(operator.clone(), "Integer overflow.").into()
+}
+
+/// Whether `value` is an instance of `type_`, which is a class or a built-in
+/// type.
+fn is_instance_of(operator: &Gc<Token>, value: &Object, type_: &Object) -> Result<bool> {
+    match (value, type_) {
+        (OInstance(instance), OClass(class) | OCallable(LoxCallable::Class(class))) => {
+            Ok(instance.class().is_subclass_of(class))
+        }
+        (_, OClass(_) | OCallable(LoxCallable::Class(_))) => Ok(false),
+        (_, OType(type_)) => Ok(type_.matches(value)),
+        _ => Err(RuntimeError::new(
+            operator.clone(),
+            "Right operand of 'is' must be a class or a type.",
+        )
+        .into()),
     }
 }
 
-fn is_truthy(object: &Object) -> bool {
+pub fn is_truthy(object: &Object) -> bool {
     match object {
         ONil => false,
         OBoolean(b) => *b,
@@ -509,13 +1091,50 @@ mod test {
 
     use std::str;
 
-    use gc::{Gc, GcCell};
+    use gc::GcCell;
 
     fn interpreter_test(
         source: &str,
         expected_output: &str,
         expected_error_count: usize,
         expected_error_message: Option<&str>,
+    ) -> Result<()> {
+        interpreter_test_with_options(
+            source,
+            InterpreterOptions::default(),
+            expected_output,
+            expected_error_count,
+            expected_error_message,
+        )
+    }
+
+    fn interpreter_test_with_options(
+        source: &str,
+        options: InterpreterOptions,
+        expected_output: &str,
+        expected_error_count: usize,
+        expected_error_message: Option<&str>,
+    ) -> Result<()> {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let interpreter =
+            Interpreter::with_options(InterpreterOutput::ByteVec(output.clone()), options);
+        run_interpreter_test(
+            source,
+            interpreter,
+            &output,
+            expected_output,
+            expected_error_count,
+            expected_error_message,
+        )
+    }
+
+    fn run_interpreter_test(
+        source: &str,
+        mut interpreter: Interpreter,
+        output: &Arc<Mutex<Vec<u8>>>,
+        expected_output: &str,
+        expected_error_count: usize,
+        expected_error_message: Option<&str>,
     ) -> Result<()> {
         let mut error_count = 0usize;
         let mut error = None;
@@ -531,9 +1150,6 @@ mod test {
         // Interpreter tests should always parse.
         assert_eq!(error_count, 0);
 
-        let output = Gc::new(GcCell::new(Vec::new()));
-        let mut interpreter = Interpreter::new(InterpreterOutput::ByteVec(output.clone()));
-
         Resolver::new(&mut interpreter, |_, _| {
             error_count += 1;
         })
@@ -552,11 +1168,11 @@ mod test {
 
         // First compare the stringified output/expected output in order to
         // get an error message in terms of strings if they don't match.
-        assert_eq!(str::from_utf8(&output.borrow())?, expected_output);
+        assert_eq!(str::from_utf8(&output.lock().unwrap())?, expected_output);
 
         // This should always pass if the above assertion passed, but let's
         // be thorough.
-        assert_eq!(*output.borrow(), expected_output.as_bytes());
+        assert_eq!(*output.lock().unwrap(), expected_output.as_bytes());
 
         if let Some(expected_error_output) = expected_error_message {
             assert_eq!(&error.unwrap().message, expected_error_output);
@@ -565,21 +1181,38 @@ mod test {
         Ok(())
     }
 
+    /// Run `source` on an existing interpreter, which must parse and resolve
+    /// it without errors.
+    fn interpret_source<F>(
+        interpreter: &mut Interpreter,
+        source: &str,
+        error_handler: F,
+    ) -> Result<Option<i32>>
+    where
+        F: FnMut(&RuntimeError),
+    {
+        let tokens = Scanner::new(source, |_, _| panic!()).scan_tokens();
+        let statements = Parser::new(tokens, |_, _| panic!()).parse()?;
+        Resolver::new(interpreter, |_, _| panic!()).resolve(&statements)?;
+        let exit = interpreter.interpret(&statements, error_handler);
+        Ok(exit.map(|exit| exit.code))
+    }
+
     #[test]
     fn evaluate() -> Result<()> {
         let error_count = GcCell::new(0usize);
 
         let tokens = vec![
             Token::new(TT::LeftParen, "(", Object::Nil, 1).into(),
-            Token::new(TT::Number, "1", Object::Number(1.0), 1).into(),
+            Token::new(TT::Number, "1", Object::Integer(1), 1).into(),
             Token::new(TT::Plus, "+", Object::Nil, 1).into(),
-            Token::new(TT::Number, "2", Object::Number(2.0), 1).into(),
+            Token::new(TT::Number, "2", Object::Integer(2), 1).into(),
             Token::new(TT::Minus, "-", Object::Nil, 1).into(),
             Token::new(TT::Number, "0.5", Object::Number(0.5), 1).into(),
             Token::new(TT::RightParen, ")", Object::Nil, 1).into(),
             Token::new(TT::Star, "*", Object::Nil, 1).into(),
             Token::new(TT::Minus, "-", Object::Nil, 1).into(),
-            Token::new(TT::Number, "4", Object::Number(4.0), 1).into(),
+            Token::new(TT::Number, "4", Object::Integer(4), 1).into(),
             Token::new(TT::Semicolon, ";", Object::Nil, 1).into(),
             Token::new(TT::Eof, "", Object::Nil, 1).into(),
         ];
@@ -863,4 +1496,1216 @@ mod test {
         let expected_output = "a\nb\n";
         interpreter_test(source, expected_output, 0, None)
     }
+
+    #[test]
+    fn operator_overloading() -> Result<()> {
+        let source = r#"
+            class Vec2 {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                }
+
+                add(other) { return Vec2(this.x + other.x, this.y + other.y); }
+                sub(other) { return Vec2(this.x - other.x, this.y - other.y); }
+                mul(k) { return Vec2(this.x * k, this.y * k); }
+                neg() { return Vec2(-this.x, -this.y); }
+                lt(other) { return this.x * this.x + this.y * this.y <
+                                   other.x * other.x + other.y * other.y; }
+                equals(other) { return this.x == other.x and this.y == other.y; }
+                toString() { return "a vector"; }
+            }
+
+            var a = Vec2(1, 2);
+            var b = Vec2(3, 4);
+            var c = a + b;
+            print c.x; print c.y;
+            print (b - a).x;
+            print (a * 3).y;
+            print (-a).x;
+            print a < b;
+            print b < a;
+            print a == Vec2(1, 2);
+            print a != Vec2(1, 2);
+            print a == nil;
+            print nil != a;
+            print a;
+            print 1 + a;
+        "#;
+        let expected_output =
+            "4\n6\n2\n6\n-1\ntrue\nfalse\ntrue\nfalse\nfalse\ntrue\na vector\n";
+        let expected_error_message = Some("Operands must be two numbers or two strings.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn operators_without_special_methods() -> Result<()> {
+        let source = r#"
+            class Foo {}
+            var foo = Foo();
+            print foo == foo;
+            print foo == Foo();
+            print foo;
+            print foo + 1;
+        "#;
+        let expected_output = "true\nfalse\nFoo instance\n";
+        let expected_error_message = Some("Operands must be two numbers or two strings.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn special_method_arity() -> Result<()> {
+        let source = r#"
+            class Foo {
+                add() { return 1; }
+            }
+            print Foo() + Foo();
+        "#;
+        let expected_output = "";
+        let expected_error_message =
+            Some("Expected 'add' to take 1 arguments but it takes 0.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn default_parameters() -> Result<()> {
+        let source = r#"
+            var base = 10;
+            fun f(a, b = a + base, c = "c") {
+                print a;
+                print b;
+                print c;
+            }
+            f(1);
+            f(1, 2, 3);
+            base = 20;
+            f(1);
+        "#;
+        let expected_output = "1\n11\nc\n1\n2\n3\n1\n21\nc\n";
+        interpreter_test(source, expected_output, 0, None)
+    }
+
+    #[test]
+    fn rest_parameters() -> Result<()> {
+        let source = r#"
+            fun f(first, ...rest) {
+                print first;
+                print rest;
+                print rest.length();
+            }
+            f(1);
+            f(1, 2, "three");
+        "#;
+        let expected_output = "1\n[]\n0\n1\n[2, three]\n2\n";
+        interpreter_test(source, expected_output, 0, None)
+    }
+
+    #[test]
+    fn named_arguments() -> Result<()> {
+        let source = r#"
+            fun f(a, b = 2, c = 3) {
+                print a + b + c;
+            }
+            f(1, c: 10);
+            f(c: 100, a: 1);
+
+            class Point {
+                init(x, y = 0) {
+                    this.x = x;
+                    this.y = y;
+                }
+            }
+            var p = Point(y: 2, x: 1);
+            print p.x;
+            print p.y;
+            f(1, a: 2);
+        "#;
+        let expected_output = "13\n103\n1\n2\n";
+        let expected_error_message = Some("Got multiple values for parameter 'a'.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn unknown_named_argument() -> Result<()> {
+        let source = r"
+            fun f(a) {}
+            f(1, b: 2);
+        ";
+        let expected_error_message = Some("No parameter named 'b'.");
+        interpreter_test(source, "", 1, expected_error_message)
+    }
+
+    #[test]
+    fn default_parameter_arity() -> Result<()> {
+        let source = r"
+            fun f(a, b = 1) {}
+            f(1, 2, 3);
+        ";
+        let expected_error_message = Some("Expected 1 to 2 arguments but got 3.");
+        interpreter_test(source, "", 1, expected_error_message)
+    }
+
+    #[test]
+    fn lists() -> Result<()> {
+        let source = r#"
+            var list = List(1, "two");
+            list.push(3);
+            print list;
+            print list.length();
+            print list.get(1);
+            list.set(0, "one");
+            print list.pop();
+            print list;
+            list.push(list);
+            print list;
+            list.get(3);
+        "#;
+        let expected_output = "[1, two, 3]\n3\ntwo\n3\n[one, two]\n[one, two, [...]]\n";
+        let expected_error_message = Some("List index out of range.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn global_constants() -> Result<()> {
+        let source = r"
+            const LIMIT = 10;
+            print LIMIT;
+            fun bump() {
+                LIMIT = LIMIT + 1;
+            }
+            bump();
+        ";
+        let expected_output = "10\n";
+        let expected_error_message = Some("Can't assign to constant 'LIMIT'.");
+        interpreter_test(source, expected_output, 1, expected_error_message)?;
+
+        let source = r"
+            const X = 1;
+            var Y = 1;
+            var Y = 2;
+            print Y;
+            var X = 2;
+            X = 3;
+        ";
+        let expected_error_message = Some("Can't redeclare constant 'X'.");
+        interpreter_test(source, "2\n", 1, expected_error_message)?;
+        interpreter_test("const X = 1; fun X() {}", "", 1, expected_error_message)
+    }
+
+    #[test]
+    fn shadowed_constant() -> Result<()> {
+        let source = r"
+            const a = 1;
+            {
+                var a = 2;
+                a = 3;
+                print a;
+            }
+            print a;
+        ";
+        let expected_output = "3\n1\n";
+        interpreter_test(source, expected_output, 0, None)
+    }
+
+    #[test]
+    fn traits() -> Result<()> {
+        let source = r#"
+            trait Greets {
+                greet() { print "Hello from " + this.name(); }
+                describe() { print "a greeter"; }
+            }
+
+            trait Waves {
+                wave() { print this.name() + " waves"; }
+            }
+
+            class Base {
+                describe() { print "a base"; }
+                kind() { print "base"; }
+            }
+
+            class Person < Base with Greets, Waves {
+                name() { return "Person"; }
+                kind() {
+                    super.kind();
+                    print "person";
+                }
+            }
+
+            var p = Person();
+            p.greet();
+            p.wave();
+            p.describe();
+            p.kind();
+        "#;
+        let expected_output = "Hello from Person\nPerson waves\na greeter\nbase\nperson\n";
+        interpreter_test(source, expected_output, 0, None)
+    }
+
+    #[test]
+    fn mix_in_non_trait() -> Result<()> {
+        let source = r"
+            class A {}
+            class B with A {}
+        ";
+        let expected_error_message = Some("Can only mix in traits.");
+        interpreter_test(source, "", 1, expected_error_message)
+    }
+
+    #[test]
+    fn match_statement() -> Result<()> {
+        let source = r#"
+            class Point {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                }
+            }
+            class Point3 < Point {}
+
+            fun describe(value) {
+                match (value) {
+                    1 => print "one";
+                    -1 => print "minus one";
+                    "x" => print "the letter x";
+                    nil => print "nothing";
+                    Point(x: 0, y) => print y;
+                    Point(x, y) if x == y => {
+                        print "diagonal";
+                        print x;
+                    }
+                    Point() => print "some point";
+                    n if n == 1000 => print "big";
+                    _ => print "something else";
+                }
+            }
+
+            describe(1);
+            describe(-1);
+            describe("x");
+            describe(nil);
+            describe(Point(0, 7));
+            describe(Point3(3, 3));
+            describe(Point(1, 2));
+            describe(1000);
+            describe(true);
+        "#;
+        let expected_output = "one\nminus one\nthe letter x\nnothing\n7\ndiagonal\n3\n\
+                               some point\nbig\nsomething else\n";
+        interpreter_test(source, expected_output, 0, None)
+    }
+
+    #[test]
+    fn match_without_matching_arm() -> Result<()> {
+        let source = r#"
+            match (3) {
+                1 => { print "one"; },
+                2 => { print "two"; },
+            }
+        "#;
+        let expected_error_message = Some("No match arm matched the value '3'.");
+        interpreter_test(source, "", 1, expected_error_message)
+    }
+
+    #[test]
+    fn enums() -> Result<()> {
+        let source = r#"
+            enum Color { Red, Green, Blue, }
+            print Color;
+            print Color.Green;
+            print Color.Green.name;
+            print Color.Blue.ordinal;
+            print Color.values();
+            print Color.Red == Color.Red;
+            print Color.Red == Color.Green;
+            fun describe(color) {
+                match (color) {
+                    Color.Red => print "warm";
+                    _ => print "cool";
+                }
+            }
+            describe(Color.Red);
+            describe(Color.Blue);
+            print Color.Purple;
+        "#;
+        let expected_output =
+            "Color\nColor.Green\nGreen\n2\n[Color.Red, Color.Green, Color.Blue]\n\
+                               true\nfalse\nwarm\ncool\n";
+        let expected_error_message = Some("Undefined variant 'Purple' of enum 'Color'.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn maps() -> Result<()> {
+        let source = r#"
+            enum Color { Red, Green }
+            var map = Map();
+            map.set(Color.Red, "red");
+            map.set("one", 1);
+            map.set(0, "zero");
+            print map;
+            print map.get(Color.Red);
+            print map.get(Color.Green);
+            print map.get(-0);
+            print map.has("one");
+            map.set(Color.Red, "crimson");
+            print map.remove("one");
+            print map.keys();
+            print map.values();
+            print map.length();
+            map.set("self", map);
+            print map;
+            map.set(List(), 1);
+        "#;
+        let expected_output = "{Color.Red: red, one: 1, 0: zero}\nred\nnil\nzero\ntrue\n1\n\
+                               [Color.Red, 0]\n[crimson, zero]\n2\n\
+                               {Color.Red: crimson, 0: zero, self: {...}}\n";
+        let expected_error_message =
+            Some("Only nil, booleans, numbers, strings and enum values can be map keys.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn generators() -> Result<()> {
+        let source = r#"
+            fun count(from, to) {
+                var i = from;
+                while (i <= to) {
+                    if (i == 3) {
+                        i = i + 1;
+                    } else {
+                        yield i;
+                        i = i + 1;
+                    }
+                }
+                yield "done";
+            }
+            var g = count(1, 4);
+            print g;
+            print g.next();
+            print g.hasNext();
+            print g.next();
+            for (var x in g) print x;
+            print g.hasNext();
+            print g.next();
+        "#;
+        let expected_output = "<generator count>\n1\ntrue\n2\n4\ndone\nfalse\nnil\n";
+        interpreter_test(source, expected_output, 0, None)
+    }
+
+    #[test]
+    fn generator_locals_and_nesting() -> Result<()> {
+        let source = r#"
+            fun pairs(list) {
+                for (var a in list) {
+                    var doubled = a * 2;
+                    for (var b in list) {
+                        match (b) {
+                            1 => yield List(a, b, doubled);
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            fun take(n) {
+                for (var i = 0; i < n; i = i + 1) {
+                    yield i;
+                    if (i == 1) return;
+                }
+            }
+            for (var p in pairs(List(1, 2))) print p;
+            for (var i in take(5)) print i;
+            var map = Map();
+            map.set("a", 1);
+            map.set("b", 2);
+            for (var key in map) print key;
+        "#;
+        let expected_output = "[1, 1, 2]\n[2, 1, 4]\n0\n1\na\nb\n";
+        interpreter_test(source, expected_output, 0, None)
+    }
+
+    #[test]
+    fn generator_errors_propagate() -> Result<()> {
+        let source = r#"
+            fun broken() {
+                yield 1;
+                yield nil + 1;
+            }
+            var g = broken();
+            print g.next();
+            g.next();
+        "#;
+        let expected_error_message = Some("Operands must be two numbers or two strings.");
+        interpreter_test(source, "1\n", 1, expected_error_message)
+    }
+
+    #[test]
+    fn iterate_non_iterable() -> Result<()> {
+        let source = r"
+            for (var x in 1) print x;
+        ";
+        let expected_error_message =
+            Some("Can only iterate over lists, maps and generators.");
+        interpreter_test(source, "", 1, expected_error_message)
+    }
+
+    #[test]
+    fn fibers_and_channels() -> Result<()> {
+        let source = r#"
+            var channel = Channel();
+            fun producer(name, count) {
+                for (var i = 0; i < count; i = i + 1) {
+                    print name + " sends";
+                    channel.send(i);
+                }
+            }
+            spawn(producer, "a", 2);
+            spawn(producer, "b", 1);
+            print "main";
+            for (var i = 0; i < 3; i = i + 1) print channel.receive();
+        "#;
+        let expected_output = "main\na sends\n0\nb sends\n0\na sends\n1\n";
+        interpreter_test(source, expected_output, 0, None)
+    }
+
+    #[test]
+    fn buffered_channel() -> Result<()> {
+        let source = r#"
+            var results = Channel(2);
+            fun worker(n) {
+                results.send(n * n);
+                print "worker " + "done";
+            }
+            spawn(worker, 2);
+            spawn(worker, 3);
+            print results.receive() + results.receive();
+        "#;
+        let expected_output = "worker done\nworker done\n13\n";
+        interpreter_test(source, expected_output, 0, None)
+    }
+
+    #[test]
+    fn fibers_run_when_script_ends() -> Result<()> {
+        let source = r#"
+            fun hello() {
+                print "fiber";
+            }
+            spawn(hello);
+            print "main";
+        "#;
+        interpreter_test(source, "main\nfiber\n", 0, None)
+    }
+
+    #[test]
+    fn deadlock() -> Result<()> {
+        let source = r"
+            var channel = Channel();
+            fun stuck() {
+                channel.receive();
+            }
+            spawn(stuck);
+            spawn(stuck);
+        ";
+        let expected_error_message = Some("Deadlock: every fiber is blocked.");
+        interpreter_test(source, "", 1, expected_error_message)
+    }
+
+    #[test]
+    fn errors_in_fibers() -> Result<()> {
+        let source = r#"
+            var channel = Channel();
+            fun broken() {
+                channel.send(1);
+                print nil + 1;
+            }
+            spawn(broken);
+            print channel.receive();
+            channel.receive();
+        "#;
+        let expected_error_message = Some("Operands must be two numbers or two strings.");
+        interpreter_test(source, "1\n", 1, expected_error_message)
+    }
+
+    #[test]
+    fn isolates() -> Result<()> {
+        let worker = r#"
+            class Point {
+                sum() {
+                    return this.x + this.y;
+                }
+            }
+            var sum = receiveMessage().sum();
+            print format("worker sum {}", sum);
+            postMessage(sum);
+            var list = receiveMessage();
+            list.push("worker");
+            postMessage(list);
+        "#;
+        let path = std::env::temp_dir().join(format!("isolate-{}.lox", std::process::id()));
+        std::fs::write(&path, worker)?;
+
+        let source = format!(
+            r#"
+            class Point {{
+                init(x, y) {{
+                    this.x = x;
+                    this.y = y;
+                }}
+            }}
+            var worker = Isolate("{}");
+            worker.post(Point(1, 2));
+            print worker.receive();
+            var list = List(1);
+            worker.post(list);
+            print worker.receive();
+            print list;
+            print worker.receive();
+            worker.join();
+            worker.post(1);
+        "#,
+            path.display()
+        );
+        let expected_output = "worker sum 3\n3\n[1, worker]\n[1]\nnil\n";
+        let expected_error_message = Some("Isolate has already been joined.");
+        let result = interpreter_test(&source, expected_output, 1, expected_error_message);
+        std::fs::remove_file(&path)?;
+        result
+    }
+
+    #[test]
+    fn tail_calls() -> Result<()> {
+        let source = r#"
+            fun count(n, total) {
+                if (n == 0) return total;
+                return count(n - 1, total + 1);
+            }
+            print count(100000, 0);
+
+            fun isEven(n) {
+                if (n == 0) return true;
+                return isOdd(n - 1);
+            }
+            fun isOdd(n) {
+                if (n == 0) return false;
+                return isEven(n - 1);
+            }
+            print isEven(100000);
+
+            class Counter {
+                down(n) {
+                    if (n == 0) return "done";
+                    return this.down(n - 1);
+                }
+            }
+            print Counter().down(100000);
+
+            fun fail(n) {
+                if (n == 0) return nil.field;
+                return fail(n - 1);
+            }
+            fail(100000);
+        "#;
+        let expected_output = "100000\ntrue\ndone\n";
+        let expected_error_message = Some("Only instances have properties.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn integers() -> Result<()> {
+        let source = r#"
+            print 9007199254740993 + 2;
+            print 1 + 0.5;
+            print 2.0;
+            print 6 / 3;
+            print 7 / 2;
+            print 1 == 1.0;
+            print 9007199254740993 > 9007199254740992.0;
+            print 1 / 0;
+            var map = Map();
+            map.set(2.0, "two");
+            print map.get(2);
+            print -9223372036854775807 - 1;
+            print 9223372036854775807 + 1;
+        "#;
+        let expected_output =
+            "9007199254740995\n1.5\n2.0\n2\n3.5\ntrue\ntrue\ninf\ntwo\n-9223372036854775808\n";
+        interpreter_test(source, expected_output, 1, Some("Integer overflow."))
+    }
+
+    #[test]
+    fn number_formatting() -> Result<()> {
+        let source = r#"
+            print 0xff + 0b11 + 0o10 + 1_000;
+            print 1.5e3;
+            print toFixed(3.14159, 2);
+            print toFixed(2, 1);
+            print toPrecision(123456, 2);
+            print toPrecision(0.000123, 2);
+            print toPrecision(1.5, 4);
+            print toRadix(-255, 16);
+            print toRadix(5, 2);
+            toRadix(5, 1);
+        "#;
+        let expected_output = "1266\n1500.0\n3.14\n2.0\n1.2e+5\n0.00012\n1.500\n-ff\n101\n";
+        let expected_error_message = Some("Radix must be between 2 and 36.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn java_number_format() -> Result<()> {
+        let source = r#"
+            print 1e21;
+            print List(1e21, 2.0, 3);
+            print format("{} {:.1}", 1.5e-5, 2);
+            print join(List(1e7), "");
+            print toJson(List(1e21, 2.0));
+        "#;
+        let options = InterpreterOptions {
+            java_number_format: true,
+            ..Default::default()
+        };
+        let expected_output = concat!(
+            "1.0E21\n",
+            "[1.0E21, 2, 3]\n",
+            "1.5E-5 2.0\n",
+            "1.0E7\n",
+            "[1000000000000000000000.0,2.0]\n",
+        );
+        interpreter_test_with_options(source, options, expected_output, 0, None)
+    }
+
+    #[test]
+    fn reflection() -> Result<()> {
+        let source = r#"
+            class Shape {
+                area() { return 0; }
+                name() { return "shape"; }
+            }
+            class Square < Shape {
+                init(side) { this.side = side; }
+                area() { return this.side * this.side; }
+            }
+            var square = Square(2);
+            print typeOf(square) + " " + typeOf(1) + " " + typeOf(1.5) + " " + typeOf(Square);
+            print methods(Square);
+            print classOf(square) == Square;
+            print superclassOf(Square);
+            print superclassOf(Shape);
+            setField(square, "color", "red");
+            print fields(square);
+            print getField(square, "color");
+            print hasField(square, "side");
+            print deleteField(square, "side");
+            print hasField(square, "side");
+            print nameOf(square.area) + " " + nameOf(Square) + " " + nameOf(clock);
+            print arityOf(Square);
+            getField(square, "side");
+        "#;
+        let expected_output = "instance integer number class\n\
+                               [area, init, name]\n\
+                               true\n\
+                               Shape\n\
+                               nil\n\
+                               [color, side]\n\
+                               red\n\
+                               true\n\
+                               true\n\
+                               false\n\
+                               area Square clock\n\
+                               1\n";
+        let expected_error_message = Some("Undefined field 'side'.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn is_operator() -> Result<()> {
+        let source = r#"
+            class Shape {}
+            class Circle < Shape {}
+            class Other {}
+            var circle = Circle();
+            print circle is Circle;
+            print circle is Shape;
+            print circle is Other;
+            print Shape() is Circle;
+            print 1 is Number and 1.5 is Number and 1 is Integer;
+            print 1.5 is Integer;
+            print "a" is String and true is Bool and nil is Nil;
+            print clock is Function and Shape is Class and circle is Class;
+            print !(circle is Shape) == false;
+            print circle is 1;
+        "#;
+        let expected_output = "true\ntrue\nfalse\nfalse\ntrue\nfalse\ntrue\nfalse\ntrue\n";
+        let expected_error_message =
+            Some("Right operand of 'is' must be a class or a type.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn math() -> Result<()> {
+        let source = r#"
+            print sqrt(16) + pow(2, 10);
+            print floor(2.7) + ceil(2.2) + round(-2.5) + trunc(-2.7);
+            print floor(5);
+            print abs(-3) + abs(-0.5);
+            print min(3, 1.5, 2) + max(1, 7, 2);
+            print max(1, NAN);
+            print round(sin(PI / 2) + cos(0) + log(E) + log(8, 2) + exp(0));
+            print atan2(1, 1) == PI / 4;
+            seedRandom(42);
+            var first = random();
+            var roll = randomInt(1, 6);
+            seedRandom(42);
+            print first == random() and roll == randomInt(1, 6);
+            print roll >= 1 and roll <= 6 and first >= 0 and first < 1;
+            randomInt(6, 1);
+        "#;
+        let expected_output = "1028.0\n0\n5\n3.5\n8.5\nNaN\n7\ntrue\ntrue\ntrue\n";
+        let expected_error_message =
+            Some("Expect the low bound to be at most the high bound.");
+        interpreter_test(source, expected_output, 1, expected_error_message)
+    }
+
+    #[test]
+    fn strings() -> Result<()> {
+        let source = r#"
+            var s = "  Hello, wörld  ";
+            print "[" + trim(s) + "]";
+            s = trim(s);
+            print length(s);
+            print substring(s, 7) + substring(s, 0, 5);
+            print indexOf(s, "wö") + indexOf(s, "x");
+            print contains(s, "llo") and startsWith(s, "He") and endsWith(s, "ld");
+            print split("a,b,,c", ",");
+            print join(List(1, "b", nil), "-");
+            print replace("a.b.c", ".", "::");
+            print upper(s) + " " + lower(s);
+            print repeat("ab", 3);
+            print charCode("A") + charCode(s, 8);
+            print fromCharCode(955);
+            print parseNumber(" 42 ") + parseNumber("0.5") + parseNumber("1e3");
+            print parseNumber("4x2");
+            print "apple" < "banana" and "b" > "a" and "ab" >= "ab" and !("b" <= "a");
+            "a" < 1;
+        "#;
+        let expected_output = "[Hello, wörld]\n\
+                               12\n\
+                               wörldHello\n\
+                               6\n\
+                               true\n\
+                               [a, b, , c]\n\
+                               1-b-nil\n\
+                               a::b::c\n\
+                               HELLO, WÖRLD hello, wörld\n\
+                               ababab\n\
+                               311\n\
+                               λ\n\
+                               1042.5\n\
+                               nil\n\
+                               true\n";
+        interpreter_test(
+            source,
+            expected_output,
+            1,
+            Some("Operands must be two numbers or two strings."),
+        )?;
+
+        interpreter_test(
+            "repeat(\"ab\", 9223372036854775807);",
+            "",
+            1,
+            Some("Repeated string would be too long."),
+        )
+    }
+
+    #[test]
+    fn file_system() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("lox-tw-fs-{}", std::process::id()));
+        let source = format!(
+            r#"
+            var dir = "{}";
+            var nl = fromCharCode(10);
+            createDir(dir + "/sub");
+            writeFile(dir + "/a.txt", "one" + nl);
+            appendFile(dir + "/a.txt", "two" + nl);
+            print readFile(dir + "/a.txt") == "one" + nl + "two" + nl;
+            print readLines(dir + "/a.txt");
+            print listDir(dir);
+            print fileExists(dir + "/a.txt") and isDir(dir + "/sub") and !isDir(dir + "/a.txt");
+            removeFile(dir + "/a.txt");
+            removeDir(dir + "/sub");
+            print listDir(dir);
+            print fileExists(dir + "/a.txt");
+            removeDir(dir);
+            readFile(dir + "/a.txt");
+        "#,
+            dir.display()
+        );
+        let expected_output = "true\n[one, two]\n[a.txt, sub]\ntrue\n[]\nfalse\n";
+        interpreter_test(&source, expected_output, 1, None)?;
+        assert!(!dir.exists());
+
+        let options = InterpreterOptions {
+            file_access: false,
+            ..Default::default()
+        };
+        let expected_error_message = Some("File access is disabled.");
+        interpreter_test_with_options(
+            r#"fileExists("a");"#,
+            options.clone(),
+            "",
+            1,
+            expected_error_message,
+        )?;
+        interpreter_test_with_options(
+            r#"Isolate("a.lox");"#,
+            options,
+            "",
+            1,
+            expected_error_message,
+        )
+    }
+
+    #[test]
+    fn reading_input() -> Result<()> {
+        let source = r#"
+            var line = readLine();
+            while (line != nil) {
+                print "[" + line + "]";
+                line = readLine();
+            }
+            print readAll();
+            print readLine();
+        "#;
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let input = InterpreterInput::from_bytes(b"one\r\ntwo\nlast".to_vec());
+        let interpreter =
+            Interpreter::new(InterpreterOutput::ByteVec(output.clone())).with_input(input);
+        let expected_output = "[one]\n[two]\n[last]\n\nnil\n";
+        run_interpreter_test(source, interpreter, &output, expected_output, 0, None)
+    }
+
+    #[test]
+    fn json() -> Result<()> {
+        let source = r#"
+            var data = parseJson(readAll());
+            print data.get("name");
+            print data.get("tags");
+            print data.get("count") is Integer;
+            print data.get("ratio");
+            print data.get("missing");
+            print toJson(data);
+            print toJson(List(1, List(), Map()), 2);
+
+            var point = parseJson("{}", true);
+            print point;
+            point.x = 1;
+            print toJson(point);
+
+            var cycle = List();
+            cycle.push(cycle);
+            toJson(cycle);
+        "#;
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let json = r#"{"name": "café 😀", "tags": ["a", "b\n"], "count": 3,
+            "ratio": 1.5e0, "missing": null, "ok": true}"#;
+        let input = InterpreterInput::from_bytes(json.as_bytes().to_vec());
+        let interpreter =
+            Interpreter::new(InterpreterOutput::ByteVec(output.clone())).with_input(input);
+        let expected_output = concat!(
+            "café 😀\n",
+            "[a, b\n]\n",
+            "true\n",
+            "1.5\n",
+            "nil\n",
+            r#"{"name":"café 😀","tags":["a","b\n"],"count":3,"ratio":1.5,"missing":null,"ok":true}"#,
+            "\n[\n  1,\n  [],\n  {}\n]\n",
+            "JsonObject instance\n",
+            "{\"x\":1}\n",
+        );
+        run_interpreter_test(
+            source,
+            interpreter,
+            &output,
+            expected_output,
+            1,
+            Some("Can't convert a value which contains itself to JSON."),
+        )?;
+
+        interpreter_test(
+            "parseJson(\"[1, 2,\n ]\");",
+            "",
+            1,
+            Some("Invalid JSON at line 2, column 2: Unexpected ']'."),
+        )?;
+        interpreter_test(
+            "parseJson(\"[1,\n 2, -1e400]\");",
+            "",
+            1,
+            Some("Invalid JSON at line 2, column 5: Number is out of range."),
+        )?;
+
+        let source = r#"
+            var nested = List();
+            for (var i = 0; i < 511; i = i + 1) nested = List(nested);
+            print length(toJson(nested));
+            toJson(List(nested));
+        "#;
+        interpreter_test(
+            source,
+            "1024\n",
+            1,
+            Some("Too deeply nested to convert to JSON."),
+        )?;
+        interpreter_test(
+            "print toJson(List(1), 10); toJson(List(1), 100000000000);",
+            "[\n          1\n]\n",
+            1,
+            Some("JSON indent must be at most 10."),
+        )
+    }
+
+    #[test]
+    fn script_arguments_and_environment() -> Result<()> {
+        let source = r#"
+            print args();
+            print getEnv("LOX_TW_UNSET_VARIABLE");
+            exit(1.5);
+        "#;
+        let options = InterpreterOptions {
+            args: vec!["one".to_owned(), "two".to_owned()],
+            ..Default::default()
+        };
+        interpreter_test_with_options(
+            source,
+            options,
+            "[one, two]\nnil\n",
+            1,
+            Some("Argument 1 to 'exit' must be an integer."),
+        )
+    }
+
+    #[test]
+    fn exit() -> Result<()> {
+        let source = r#"
+            fun f(n) {
+                if (n == 0) {
+                    print "exiting";
+                    exit(3);
+                }
+                f(n - 1);
+                print "unreachable";
+            }
+            fun fiber() { print "unreachable"; }
+            spawn(fiber);
+            f(3);
+            print "unreachable";
+        "#;
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut interpreter = Interpreter::new(InterpreterOutput::ByteVec(output.clone()));
+        let code = interpret_source(&mut interpreter, source, |_| panic!())?;
+        assert_eq!(code, Some(3));
+        assert_eq!(str::from_utf8(&output.lock().unwrap())?, "exiting\n");
+        Ok(())
+    }
+
+    #[test]
+    fn dates_and_times() -> Result<()> {
+        let source = r#"
+            var leap = makeDate(2024, 2, 29, 13, 45, 30.25);
+            print leap;
+            print dateFields(leap);
+            print formatDate(leap, "%A %d %B %Y, %H:%M:%S.%f (%a %b, day %j) 100%%");
+            print parseDate("2024-02-29T14:45:30.25+01:00") == leap;
+            print formatDate(addMonths(leap, 12), "%Y-%m-%d");
+            print formatDate(addMonths(leap, -1), "%Y-%m-%d");
+            print formatDate(leap + duration(1, 12), "%Y-%m-%d %H:%M");
+            print formatDuration(duration(1, 2, 30));
+            print formatDuration(-0.5);
+            print formatDuration(0);
+
+            var start = monotonic();
+            sleep(0.01);
+            print monotonic() - start >= 0.01;
+            parseDate("2024-02-30");
+        "#;
+        let expected_output = concat!(
+            "1709214330.25\n",
+            "{year: 2024, month: 2, day: 29, hour: 13, minute: 45, second: 30, ",
+            "millisecond: 250, weekday: 4, yearDay: 60}\n",
+            "Thursday 29 February 2024, 13:45:30.250 (Thu Feb, day 060) 100%\n",
+            "true\n",
+            "2025-02-28\n",
+            "2024-01-29\n",
+            "2024-03-02 01:45\n",
+            "P1DT2H30M\n",
+            "-PT0.5S\n",
+            "PT0S\n",
+            "true\n",
+        );
+        interpreter_test(
+            source,
+            expected_output,
+            1,
+            Some("'2024-02-30' isn't a valid ISO-8601 date."),
+        )?;
+
+        interpreter_test(
+            "addMonths(0, 9223372036854775807);",
+            "",
+            1,
+            Some("Timestamp out of range."),
+        )
+    }
+
+    #[test]
+    fn higher_order_functions() -> Result<()> {
+        let source = r#"
+            fun square(x) { return x * x; }
+            fun isEven(x) { return x / 2 == floor(x / 2); }
+            fun add(a, b) { return a + b; }
+            fun byLength(a, b) { return length(a) - length(b); }
+            fun show(x) { print x; }
+
+            var numbers = List(3, 1, 4, 1, 5, 9, 2, 6);
+            print map(numbers, square);
+            print filter(numbers, isEven);
+            print reduce(numbers, add);
+            print reduce(List(), add, 0);
+            print any(numbers, isEven);
+            print all(numbers, isEven);
+            print sort(numbers);
+            print numbers;
+            print sort(List("pear", "fig", "apple", "kiwi"), byLength);
+            print sort(List("b", "a", "c"));
+            forEach(List(1, 2), show);
+
+            class Box {
+                init(value) { this.value = value; }
+            }
+            print map(numbers, Box).get(0).value;
+
+            fun pushing(x) { numbers.push(x); return true; }
+            print all(numbers, pushing);
+            print numbers.length();
+        "#;
+        let expected_output = concat!(
+            "[9, 1, 16, 1, 25, 81, 4, 36]\n",
+            "[4, 2, 6]\n",
+            "31\n",
+            "0\n",
+            "true\n",
+            "false\n",
+            "[1, 1, 2, 3, 4, 5, 6, 9]\n",
+            "[3, 1, 4, 1, 5, 9, 2, 6]\n",
+            "[fig, pear, kiwi, apple]\n",
+            "[a, b, c]\n",
+            "1\n2\n",
+            "3\n",
+            "true\n",
+            "16\n",
+        );
+        interpreter_test(source, expected_output, 0, None)?;
+
+        interpreter_test(
+            "fun compare(a, b) { return nil; } sort(List(1, 2), compare);",
+            "",
+            1,
+            Some("Comparison function must return a number."),
+        )?;
+        interpreter_test(
+            "sort(List(1, \"a\"));",
+            "",
+            1,
+            Some("Can't compare '1' and 'a'."),
+        )
+    }
+
+    #[test]
+    fn errors_in_callbacks() -> Result<()> {
+        let source = r#"
+            var list = List(1, 2, 3);
+            fun outer() {
+                var local = "local";
+                fun check(x) {
+                    if (x == 2) {
+                        return x + nil;
+                    }
+                    return true;
+                }
+                return all(list, check);
+            }
+            outer();
+        "#;
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut interpreter = Interpreter::new(InterpreterOutput::ByteVec(output.clone()));
+        let mut error = None;
+        interpret_source(&mut interpreter, source, |e| error = Some(e.clone()))?;
+        let error = error.expect("Expect the callback to fail.");
+        assert_eq!(
+            error.message,
+            "Operands must be two numbers or two strings."
+        );
+        assert_eq!(error.token.line, 7);
+
+        // The error left the interpreter back in the global environment.
+        interpret_source(
+            &mut interpreter,
+            "var after = 1; print after;",
+            |_| panic!(),
+        )?;
+        assert_eq!(str::from_utf8(&output.lock().unwrap())?, "1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn formatting() -> Result<()> {
+        let source = r#"
+            print format("{} + {} = {}", 1, 2.5, 3.5);
+            print format("{1}, {0} and {1} {{again}}", "a", "b");
+            print format("[{:5}] [{:5}] [{:>5}] [{:^7}] [{:*<6}]", 42, "ab", "ab", "mid", "x");
+            print format("[{:.2}] [{:8.3}] [{:08.2}] [{:05}] [{:.3}]", PI, 2, -PI, -42, "truncate");
+            print format("{:?} {:?} {:?}", "quoted", List(1, "two", Map()), nil);
+            print format("{:.0} {:.2} {:05.1}", 9007199254740993, -3, 7);
+            class P {
+                toString() { return "a P"; }
+            }
+            print format("{} [{:6}] {:.1}", P(), P(), P());
+            format("{2}", 1, 2);
+        "#;
+        let expected_output = concat!(
+            "1 + 2.5 = 3.5\n",
+            "b, a and b {again}\n",
+            "[   42] [ab   ] [   ab] [  mid  ] [x*****]\n",
+            "[3.14] [   2.000] [-0003.14] [-0042] [tru]\n",
+            "\"quoted\" [1, \"two\", {}] nil\n",
+            "9007199254740993 -3.00 007.0\n",
+            "a P [a P   ] a\n",
+        );
+        interpreter_test(
+            source,
+            expected_output,
+            1,
+            Some("Format argument 2 is out of range."),
+        )?;
+
+        interpreter_test(
+            "format(\"{:x}\", 1);",
+            "",
+            1,
+            Some("Invalid format specifier 'x'."),
+        )?;
+        interpreter_test(
+            "format(\"{:.70000}\", 1.5);",
+            "",
+            1,
+            Some("Invalid format specifier '.70000'."),
+        )?;
+        interpreter_test(
+            "format(\"{:99999999999}\", 1);",
+            "",
+            1,
+            Some("Invalid format specifier '99999999999'."),
+        )
+    }
 }
@@ -0,0 +1,507 @@
+use crate::environment::Environment;
+use crate::interpreter::{
+    Interpreter, InterpreterInput, InterpreterOptions, InterpreterOutput,
+};
+use crate::lox::Lox;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_class::LoxClass;
+use crate::lox_instance::LoxInstance;
+use crate::lox_list::LoxList;
+use crate::lox_map::{LoxMap, MapKey};
+use crate::lox_native::{define_native, NativeCall, NativeFn, NativeFunction};
+use crate::lox_result::Result;
+use crate::native_fs;
+use crate::object::Object;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::sync::mpsc::{self, Receiver, SendError, Sender};
+use std::thread::{self, JoinHandle};
+
+use gc::{Finalize, Gc, Trace};
+
+// Isolate threads get as much stack as the main thread usually does, since
+// the interpreter recurses on it.
+const ISOLATE_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// A value which can be posted between isolates. Each isolate has its own
+/// heap, so values are deep copied into a message when they're posted and back
+/// out of it when they're received.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    String(String),
+    List(Vec<Message>),
+    Map(Vec<(Message, Message)>),
+    /// An instance's class name and fields. Methods aren't copied: the
+    /// receiving isolate uses its own global class of the same name, if any.
+    Instance {
+        class: String,
+        fields: Vec<(String, Message)>,
+    },
+}
+
+impl Message {
+    /// Deep copy `object`, reporting errors at `token`.
+    pub fn from_object(object: &Object, token: &Gc<Token>) -> Result<Self> {
+        MessageBuilder {
+            token,
+            containing: Vec::new(),
+        }
+        .build(object)
+    }
+
+    /// Deep copy the message into the heap of `interpreter`.
+    pub fn into_object(self, interpreter: &Interpreter) -> Object {
+        self.into_object_with_classes(interpreter, &mut HashMap::new())
+    }
+
+    // `classes` holds the classes made for instances whose class isn't defined
+    // in the receiving isolate, so instances of the same class share one.
+    fn into_object_with_classes(
+        self,
+        interpreter: &Interpreter,
+        classes: &mut HashMap<String, LoxClass>,
+    ) -> Object {
+        match self {
+            Message::Nil => Object::Nil,
+            Message::Boolean(b) => Object::Boolean(b),
+            Message::Integer(n) => Object::Integer(n),
+            Message::Number(n) => Object::Number(n),
+            Message::String(s) => Object::String(Gc::new(s)),
+            Message::List(elements) => Object::List(LoxList::new(
+                elements
+                    .into_iter()
+                    .map(|e| e.into_object_with_classes(interpreter, classes))
+                    .collect(),
+            )),
+            Message::Map(entries) => {
+                let map = LoxMap::new();
+                for (key, value) in entries {
+                    let key = key.into_object_with_classes(interpreter, classes);
+                    let value = value.into_object_with_classes(interpreter, classes);
+                    let map_key = MapKey::new(&key).expect("Expect map keys to be valid.");
+                    map.insert(map_key, key, value);
+                }
+                Object::Map(map)
+            }
+            Message::Instance { class, fields } => {
+                let class = match interpreter.global(&class) {
+                    Some(Object::Class(ref class)) => class.clone(),
+                    _ => classes
+                        .entry(class.clone())
+                        .or_insert_with(|| LoxClass::new(&class, None, HashMap::new()))
+                        .clone(),
+                };
+                let instance = LoxInstance::new(class);
+                for (name, value) in fields {
+                    let value = value.into_object_with_classes(interpreter, classes);
+                    instance.set_field(&name, value);
+                }
+                Object::Instance(instance)
+            }
+        }
+    }
+}
+
+struct MessageBuilder<'a> {
+    token: &'a Gc<Token>,
+    // The addresses of the containers being copied, for detecting cycles.
+    containing: Vec<usize>,
+}
+
+impl MessageBuilder<'_> {
+    fn build(&mut self, object: &Object) -> Result<Message> {
+        match object {
+            Object::Nil => Ok(Message::Nil),
+            Object::Boolean(b) => Ok(Message::Boolean(*b)),
+            Object::Integer(n) => Ok(Message::Integer(*n)),
+            Object::Number(n) => Ok(Message::Number(*n)),
+            Object::String(s) => Ok(Message::String(s.to_string())),
+            Object::List(list) => self.container(list.address(), |builder| {
+                let elements = list
+                    .elements()
+                    .iter()
+                    .map(|e| builder.build(e))
+                    .collect::<Result<_>>()?;
+                Ok(Message::List(elements))
+            }),
+            Object::Map(map) => self.container(map.address(), |builder| {
+                let entries = map
+                    .entries()
+                    .iter()
+                    .map(|(k, v)| Ok((builder.build(k)?, builder.build(v)?)))
+                    .collect::<Result<_>>()?;
+                Ok(Message::Map(entries))
+            }),
+            Object::Instance(instance) => self.container(instance.address(), |builder| {
+                let fields = instance
+                    .fields()
+                    .iter()
+                    .map(|(name, value)| Ok((name.clone(), builder.build(value)?)))
+                    .collect::<Result<_>>()?;
+                Ok(Message::Instance {
+                    class: instance.class().to_string(),
+                    fields,
+                })
+            }),
+            _ => Err(self.error(&format!("Can't post '{object}' to another isolate."))),
+        }
+    }
+
+    fn container<F>(&mut self, address: usize, build: F) -> Result<Message>
+    where
+        F: FnOnce(&mut Self) -> Result<Message>,
+    {
+        if self.containing.contains(&address) {
+            return Err(self.error("Can't post a value which contains itself."));
+        }
+        self.containing.push(address);
+        let message = build(self);
+        self.containing.pop();
+        message
+    }
+
+    fn error(&self, message: &str) -> Box<dyn Error> {
+        RuntimeError::new(self.token.clone(), message).into()
+    }
+}
+
+/// A script running with its own interpreter on its own thread. The script
+/// talks to whoever spawned it with `postMessage` and `receiveMessage`.
+pub struct Isolate {
+    name: String,
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+    handle: JoinHandle<bool>,
+}
+
+/// How an isolate's interpreter is set up. Isolates started by a script get
+/// the settings of the script's interpreter.
+#[derive(Clone)]
+pub struct IsolateConfig {
+    pub options: InterpreterOptions,
+    pub output: InterpreterOutput,
+    pub input: InterpreterInput,
+}
+
+impl Default for IsolateConfig {
+    fn default() -> Self {
+        Self {
+            options: InterpreterOptions::default(),
+            output: InterpreterOutput::StdOut,
+            input: InterpreterInput::StdIn,
+        }
+    }
+}
+
+impl Isolate {
+    /// Run the script at `path` in a new isolate.
+    pub fn spawn(path: &str, config: IsolateConfig) -> io::Result<Self> {
+        let source = fs::read_to_string(path)?;
+        Self::from_source(path, source, config)
+    }
+
+    /// Run `source` in a new isolate, which is named `name`.
+    pub fn from_source(
+        name: &str,
+        source: String,
+        config: IsolateConfig,
+    ) -> io::Result<Self> {
+        let (sender, from_parent) = mpsc::channel();
+        let (to_parent, receiver) = mpsc::channel();
+
+        let handle = thread::Builder::new()
+            .name(name.to_owned())
+            .stack_size(ISOLATE_STACK_SIZE)
+            .spawn(move || {
+                PARENT.with(|parent| {
+                    *parent.borrow_mut() = Some(Parent {
+                        sender: to_parent,
+                        receiver: from_parent,
+                    })
+                });
+                let interpreter = Interpreter::with_options(config.output, config.options)
+                    .with_input(config.input);
+                define_isolate_globals(interpreter.globals());
+                let lox = Lox::with_interpreter(interpreter);
+                lox.run(&source);
+                !lox.had_error() && lox.exit_code().unwrap_or(0) == 0
+            })?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            sender,
+            receiver,
+            handle,
+        })
+    }
+
+    /// Post a message to the isolate. Fails if the isolate has finished.
+    pub fn post(&self, message: Message) -> std::result::Result<(), SendError<Message>> {
+        self.sender.send(message)
+    }
+
+    /// Wait for a message from the isolate. Returns `None` once the isolate
+    /// has finished and every message it posted has been received.
+    pub fn receive(&self) -> Option<Message> {
+        self.receiver.recv().ok()
+    }
+
+    /// Wait for the isolate to finish, returning whether it ran without
+    /// errors and didn't exit with a failure status. The isolate sees no more
+    /// messages from its parent.
+    pub fn join(self) -> bool {
+        drop(self.sender);
+        self.handle.join().unwrap_or(false)
+    }
+}
+
+impl fmt::Debug for Isolate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Isolate({:?})", self.name)
+    }
+}
+
+// The parent of the isolate running on this thread, if any.
+struct Parent {
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+}
+
+thread_local! {
+    static PARENT: RefCell<Option<Parent>> = const { RefCell::new(None) };
+}
+
+/// An isolate as a Lox object. It's `None` once it has been joined.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct LoxIsolate(Gc<LoxIsolateInternal>);
+
+#[derive(Debug, Finalize, Trace)]
+struct LoxIsolateInternal {
+    name: String,
+    #[unsafe_ignore_trace]
+    isolate: RefCell<Option<Isolate>>,
+}
+
+impl LoxIsolate {
+    fn new(isolate: Isolate) -> Self {
+        Self(Gc::new(LoxIsolateInternal {
+            name: isolate.name.clone(),
+            isolate: RefCell::new(Some(isolate)),
+        }))
+    }
+
+    /// Look up one of the isolate's methods.
+    pub fn get(&self, name: &Token) -> Result<Object> {
+        let (method, arity, function): (&'static str, Arity, NativeFn) =
+            match name.lexeme.as_str() {
+                "join" => ("join", Arity::exactly(0), isolate_join),
+                "post" => ("post", Arity::exactly(1), isolate_post),
+                "receive" => ("receive", Arity::exactly(0), isolate_receive),
+                _ => {
+                    return Err(RuntimeError::new(
+                        name.clone().into(),
+                        &format!("Undefined property '{}'.", &name.lexeme),
+                    )
+                    .into())
+                }
+            };
+        Ok(Object::Callable(LoxCallable::Native(
+            NativeFunction::new(method, arity, function).bind(Object::Isolate(self.clone())),
+        )))
+    }
+}
+
+impl fmt::Display for LoxIsolate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<isolate {}>", self.0.name)
+    }
+}
+
+// Isolates are compared by identity.
+impl PartialEq for LoxIsolate {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "Isolate", Arity::exactly(1), isolate);
+}
+
+/// Define the globals a script running in an isolate uses to talk to its
+/// parent.
+fn define_isolate_globals(globals: &Environment) {
+    define_native(globals, "postMessage", Arity::exactly(1), post_message);
+    define_native(
+        globals,
+        "receiveMessage",
+        Arity::exactly(0),
+        receive_message,
+    );
+}
+
+fn isolate(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    // Loading a script reads a file, so it needs file access too.
+    let path = native_fs::path(interpreter, call)?;
+    let config = IsolateConfig {
+        options: interpreter.options().clone(),
+        output: interpreter.output().clone(),
+        input: interpreter.input().clone(),
+    };
+    let isolate = Isolate::spawn(&path, config)
+        .map_err(|error| call.error(&format!("Can't start isolate '{path}': {error}.")))?;
+    Ok(Object::Isolate(LoxIsolate::new(isolate)))
+}
+
+fn this_isolate(call: &NativeCall) -> LoxIsolate {
+    if let Object::Isolate(isolate) = call.this {
+        isolate.clone()
+    } else {
+        panic!("Expect isolate methods to be bound to an isolate.");
+    }
+}
+
+fn with_isolate<T, F>(call: &NativeCall, f: F) -> Result<T>
+where
+    F: FnOnce(&Isolate) -> Result<T>,
+{
+    match this_isolate(call).0.isolate.borrow().as_ref() {
+        Some(isolate) => f(isolate),
+        None => Err(call.error("Isolate has already been joined.")),
+    }
+}
+
+fn isolate_join(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let isolate = this_isolate(call).0.isolate.borrow_mut().take();
+    let isolate = isolate.ok_or_else(|| call.error("Isolate has already been joined."))?;
+    let name = isolate.name.clone();
+    if !isolate.join() {
+        return Err(call.error(&format!("Isolate '{name}' failed.")));
+    }
+    Ok(Object::Nil)
+}
+
+fn isolate_post(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let message = Message::from_object(&call.arguments[0], call.paren)?;
+    with_isolate(call, |isolate| {
+        isolate
+            .post(message)
+            .map_err(|_| call.error("Isolate has finished."))
+    })?;
+    Ok(Object::Nil)
+}
+
+/// Returns nil once the isolate has finished and all its messages have been
+/// received.
+fn isolate_receive(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let message = with_isolate(call, |isolate| Ok(isolate.receive()))?;
+    Ok(message.map_or(Object::Nil, |m| m.into_object(interpreter)))
+}
+
+fn post_message(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let message = Message::from_object(&call.arguments[0], call.paren)?;
+    let sent = PARENT.with(|parent| match parent.borrow().as_ref() {
+        Some(parent) => parent.sender.send(message).is_ok(),
+        None => false,
+    });
+    if !sent {
+        return Err(call.error("Parent isolate has finished."));
+    }
+    Ok(Object::Nil)
+}
+
+/// Returns nil once the parent has stopped sending messages, by joining the
+/// isolate or finishing.
+fn receive_message(interpreter: &mut Interpreter, _call: &NativeCall) -> Result<Object> {
+    let message = PARENT.with(|parent| {
+        parent
+            .borrow()
+            .as_ref()
+            .and_then(|parent| parent.receiver.recv().ok())
+    });
+    Ok(message.map_or(Object::Nil, |m| m.into_object(interpreter)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::token_type::TokenType;
+
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn post_and_receive() -> Result<()> {
+        let source = r"
+            var message = receiveMessage();
+            while (message != nil) {
+                postMessage(List(message.name, message.count * 2));
+                message = receiveMessage();
+            }
+        ";
+        let isolate =
+            Isolate::from_source("doubler", source.to_owned(), Default::default())?;
+        for count in 1..=2 {
+            isolate.post(Message::Instance {
+                class: "Request".to_owned(),
+                fields: vec![
+                    ("count".to_owned(), Message::Integer(count)),
+                    ("name".to_owned(), Message::String("n".to_owned())),
+                ],
+            })?;
+            let expected =
+                vec![Message::String("n".to_owned()), Message::Integer(count * 2)];
+            assert_eq!(isolate.receive(), Some(Message::List(expected)));
+        }
+        assert!(isolate.join());
+        Ok(())
+    }
+
+    #[test]
+    fn failing_isolate() -> Result<()> {
+        let isolate = Isolate::from_source(
+            "failing",
+            "print nil + 1;".to_owned(),
+            Default::default(),
+        )?;
+        assert_eq!(isolate.receive(), None);
+        assert!(!isolate.join());
+        Ok(())
+    }
+
+    #[test]
+    fn shared_output_and_input() -> Result<()> {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let config = IsolateConfig {
+            output: InterpreterOutput::ByteVec(output.clone()),
+            input: InterpreterInput::from_bytes(b"line\n".to_vec()),
+            ..Default::default()
+        };
+        let isolate = Isolate::from_source("echo", "print readLine();".to_owned(), config)?;
+        assert!(isolate.join());
+        assert_eq!(*output.lock().unwrap(), b"line\n");
+        Ok(())
+    }
+
+    #[test]
+    fn cycles_are_not_posted() {
+        let token = Gc::new(Token::new(TokenType::Identifier, "post", Object::Nil, 1));
+        let list = LoxList::new(vec![Object::Number(1.0)]);
+        list.push(Object::List(list.clone()));
+        let error = Message::from_object(&Object::List(list), &token).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<RuntimeError>().unwrap().message,
+            "Can't post a value which contains itself."
+        );
+    }
+}
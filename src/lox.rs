@@ -1,4 +1,4 @@
-use crate::interpreter::{Interpreter, InterpreterOutput};
+use crate::interpreter::{Interpreter, InterpreterOptions, InterpreterOutput};
 use crate::lox_result::Result;
 use crate::parser::Parser;
 use crate::resolver::Resolver;
@@ -15,21 +15,43 @@ use std::process;
 pub struct Lox {
     had_error: RefCell<bool>,
     had_runtime_error: RefCell<bool>,
+    exit_code: RefCell<Option<i32>>,
     interpreter: RefCell<Interpreter>,
 }
 
 impl Lox {
-    pub fn new() -> Self {
+    pub fn with_options(options: InterpreterOptions) -> Self {
+        Self::with_interpreter(Interpreter::with_options(
+            InterpreterOutput::StdOut,
+            options,
+        ))
+    }
+
+    pub fn with_interpreter(interpreter: Interpreter) -> Self {
         Self {
             had_error: false.into(),
             had_runtime_error: false.into(),
-            interpreter: Interpreter::new(InterpreterOutput::StdOut).into(),
+            exit_code: None.into(),
+            interpreter: interpreter.into(),
         }
     }
 
+    /// Whether running a script has reported any errors.
+    pub fn had_error(&self) -> bool {
+        *self.had_error.borrow() || *self.had_runtime_error.borrow()
+    }
+
+    /// The status a script asked to exit with by calling `exit`.
+    pub fn exit_code(&self) -> Option<i32> {
+        *self.exit_code.borrow()
+    }
+
     pub fn run_file(&mut self, path: &str) -> Result<()> {
         let bytes = fs::read(path)?;
         self.run(&String::from_utf8(bytes)?);
+        if let Some(code) = self.exit_code() {
+            process::exit(code);
+        }
         if *self.had_error.borrow() {
             process::exit(65);
         }
@@ -48,6 +70,9 @@ impl Lox {
                 Ok(0) => break,
                 Ok(_) => {
                     self.run(&line);
+                    if let Some(code) = self.exit_code() {
+                        process::exit(code);
+                    }
                     *self.had_error.borrow_mut() = false
                 }
                 Err(error) => eprintln!("IO error: {error}"),
@@ -60,7 +85,7 @@ impl Lox {
         Ok(())
     }
 
-    fn run(&self, source: &str) {
+    pub fn run(&self, source: &str) {
         let tokens = Scanner::new(source, |l, m| self.line_error(l, m)).scan_tokens();
 
         let statements = Parser::new(tokens, |t, m| self.token_error(&t, m))
@@ -81,9 +106,11 @@ impl Lox {
             return;
         }
 
-        self.interpreter
+        let exit = self
+            .interpreter
             .borrow_mut()
             .interpret(&statements, |e| self.runtime_error(e));
+        *self.exit_code.borrow_mut() = exit.map(|exit| exit.code);
     }
 
     fn line_error(&self, line: usize, message: &str) {
@@ -1,40 +1,67 @@
 use crate::interpreter::Interpreter;
 use crate::lox_class::LoxClass;
 use crate::lox_function::LoxFunction;
+use crate::lox_native::NativeFunction;
 use crate::lox_result::Result;
 use crate::object::Object;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
 use crate::unique_id::unique_u128;
 
 use std::fmt::{self, Debug, Display};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use gc::{Finalize, Trace};
+use gc::{Finalize, Gc, Trace};
 
 #[derive(Clone, Debug, Finalize, Trace)]
 pub enum LoxCallable {
     Class(LoxClass),
     Clock(Clock),
     Function(LoxFunction),
+    Native(NativeFunction),
 }
 
 impl LoxCallable {
-    pub fn arity(&self) -> usize {
-        match self {
-            LoxCallable::Class(c) => c.arity(),
-            LoxCallable::Clock(c) => c.arity(),
-            LoxCallable::Function(c) => c.arity(),
-        }
-    }
-
+    /// `paren` is the token that runtime errors raised by the call itself, rather
+    /// than by the callee's body, are reported at.
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
+        paren: &Gc<Token>,
         arguments: &[Object],
+        named_arguments: &[(Gc<Token>, Object)],
     ) -> Result<Object> {
         match self {
-            LoxCallable::Class(c) => c.call(interpreter, arguments),
-            LoxCallable::Clock(c) => c.call(interpreter, arguments),
-            LoxCallable::Function(c) => c.call(interpreter, arguments),
+            LoxCallable::Class(c) => c.call(interpreter, paren, arguments, named_arguments),
+            LoxCallable::Clock(c) => {
+                check_native_arguments(c.arity(), paren, arguments, named_arguments)?;
+                c.call(interpreter, arguments)
+            }
+            LoxCallable::Function(c) => {
+                c.call(interpreter, paren, arguments, named_arguments)
+            }
+            LoxCallable::Native(c) => {
+                check_native_arguments(c.arity(), paren, arguments, named_arguments)?;
+                c.call(interpreter, paren, arguments)
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            LoxCallable::Class(c) => c.name(),
+            LoxCallable::Clock(_) => "clock",
+            LoxCallable::Function(c) => c.name(),
+            LoxCallable::Native(c) => c.name(),
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        match self {
+            LoxCallable::Class(c) => c.arity(),
+            LoxCallable::Clock(c) => c.arity(),
+            LoxCallable::Function(c) => c.arity(),
+            LoxCallable::Native(c) => c.arity(),
         }
     }
 
@@ -43,6 +70,7 @@ impl LoxCallable {
             LoxCallable::Class(c) => c.id(),
             LoxCallable::Clock(c) => c.id(),
             LoxCallable::Function(c) => c.id(),
+            LoxCallable::Native(c) => c.id(),
         }
     }
 }
@@ -53,6 +81,7 @@ impl Display for LoxCallable {
             LoxCallable::Class(c) => Display::fmt(c, f),
             LoxCallable::Clock(c) => Display::fmt(c, f),
             LoxCallable::Function(c) => Display::fmt(c, f),
+            LoxCallable::Native(c) => Display::fmt(c, f),
         }
     }
 }
@@ -63,6 +92,70 @@ impl PartialEq for LoxCallable {
     }
 }
 
+/// The number of positional arguments a callable accepts. `max` is `None` for
+/// callables taking any number of extra arguments.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exactly(n: usize) -> Self {
+        Self {
+            min: n,
+            max: Some(n),
+        }
+    }
+
+    pub fn range(min: usize, max: usize) -> Self {
+        Self {
+            min,
+            max: Some(max),
+        }
+    }
+
+    pub fn at_least(min: usize) -> Self {
+        Self { min, max: None }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+
+    pub fn check(&self, paren: &Gc<Token>, count: usize) -> Result<()> {
+        if self.accepts(count) {
+            return Ok(());
+        }
+        let expected = match self.max {
+            Some(max) if max == self.min => format!("{max}"),
+            Some(max) => format!("{} to {max}", self.min),
+            None => format!("at least {}", self.min),
+        };
+        Err(RuntimeError::new(
+            paren.clone(),
+            &format!("Expected {expected} arguments but got {count}."),
+        )
+        .into())
+    }
+}
+
+fn check_native_arguments(
+    arity: Arity,
+    paren: &Gc<Token>,
+    arguments: &[Object],
+    named_arguments: &[(Gc<Token>, Object)],
+) -> Result<()> {
+    if let Some((name, _)) = named_arguments.first() {
+        return Err(RuntimeError::new(
+            name.clone(),
+            "Native functions don't take named arguments.",
+        )
+        .into());
+    }
+    arity.check(paren, arguments.len())
+}
+
 #[derive(Clone, Debug, Finalize, Trace)]
 pub struct Clock {
     id: u128,
@@ -73,8 +166,8 @@ impl Clock {
         Self { id: unique_u128() }
     }
 
-    fn arity(&self) -> usize {
-        0
+    fn arity(&self) -> Arity {
+        Arity::exactly(0)
     }
 
     fn call(&self, _interpreter: &mut Interpreter, _arguments: &[Object]) -> Result<Object> {
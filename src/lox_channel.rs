@@ -0,0 +1,169 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_native::{define_native, NativeCall, NativeFn, NativeFunction};
+use crate::lox_result::Result;
+use crate::object::Object;
+use crate::runtime_error::RuntimeError;
+use crate::scheduler;
+use crate::token::Token;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+
+use gc::{Finalize, Gc, GcCell, Trace};
+
+/// A queue which fibers communicate over. `send` blocks until the value has
+/// been received, or until there's room for it if the channel has a capacity,
+/// and `receive` blocks until there's a value.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct LoxChannel(Gc<GcCell<ChannelState>>);
+
+#[derive(Debug, Finalize, Trace)]
+struct ChannelState {
+    values: VecDeque<Object>,
+    capacity: usize,
+    sent: usize,
+    received: usize,
+    // The ids of the fibers blocked on the channel.
+    waiters: Vec<usize>,
+}
+
+impl LoxChannel {
+    pub fn new(capacity: usize) -> Self {
+        Self(Gc::new(GcCell::new(ChannelState {
+            values: VecDeque::new(),
+            capacity,
+            sent: 0,
+            received: 0,
+            waiters: Vec::new(),
+        })))
+    }
+
+    pub fn send(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Gc<Token>,
+        value: Object,
+    ) -> Result<()> {
+        let ticket = {
+            let mut state = self.0.borrow_mut();
+            state.values.push_back(value);
+            state.sent += 1;
+            state.sent
+        };
+        self.wake_waiters(interpreter);
+
+        loop {
+            let delivered = {
+                let state = self.0.borrow();
+                state.received + state.capacity >= ticket
+            };
+            if delivered {
+                return Ok(());
+            }
+            self.wait(interpreter, token)?;
+        }
+    }
+
+    pub fn receive(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Gc<Token>,
+    ) -> Result<Object> {
+        loop {
+            let value = {
+                let mut state = self.0.borrow_mut();
+                let value = state.values.pop_front();
+                if value.is_some() {
+                    state.received += 1;
+                }
+                value
+            };
+            if let Some(value) = value {
+                self.wake_waiters(interpreter);
+                return Ok(value);
+            }
+            self.wait(interpreter, token)?;
+        }
+    }
+
+    fn wait(&self, interpreter: &mut Interpreter, token: &Gc<Token>) -> Result<()> {
+        let current = interpreter.scheduler().current();
+        self.0.borrow_mut().waiters.push(current);
+        scheduler::wait(interpreter, token)
+    }
+
+    /// Wake every fiber blocked on the channel, so they can check whether they
+    /// can continue.
+    fn wake_waiters(&self, interpreter: &mut Interpreter) {
+        let waiters = mem::take(&mut self.0.borrow_mut().waiters);
+        for id in waiters {
+            interpreter.scheduler().wake(id);
+        }
+    }
+
+    /// Look up one of the channel's methods.
+    pub fn get(&self, name: &Token) -> Result<Object> {
+        let (method, arity, function): (&'static str, Arity, NativeFn) =
+            match name.lexeme.as_str() {
+                "receive" => ("receive", Arity::exactly(0), channel_receive),
+                "send" => ("send", Arity::exactly(1), channel_send),
+                _ => {
+                    return Err(RuntimeError::new(
+                        name.clone().into(),
+                        &format!("Undefined property '{}'.", &name.lexeme),
+                    )
+                    .into())
+                }
+            };
+        Ok(Object::Callable(LoxCallable::Native(
+            NativeFunction::new(method, arity, function).bind(Object::Channel(self.clone())),
+        )))
+    }
+}
+
+impl fmt::Display for LoxChannel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<channel>")
+    }
+}
+
+// Channels are compared by identity.
+impl PartialEq for LoxChannel {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "Channel", Arity::range(0, 1), channel);
+}
+
+/// `Channel(capacity)`, where the capacity defaults to 0.
+fn channel(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let capacity = match call.arguments.first() {
+        Some(_) => call.index(0)?,
+        None => 0,
+    };
+    Ok(Object::Channel(LoxChannel::new(capacity)))
+}
+
+fn this_channel(call: &NativeCall) -> LoxChannel {
+    if let Object::Channel(channel) = call.this {
+        channel.clone()
+    } else {
+        panic!("Expect channel methods to be bound to a channel.");
+    }
+}
+
+fn channel_receive(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    this_channel(call).receive(interpreter, call.paren)
+}
+
+fn channel_send(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let value = call.arguments[0].clone();
+    this_channel(call).send(interpreter, call.paren, value)?;
+    Ok(Object::Nil)
+}
@@ -1,8 +1,10 @@
 use crate::interpreter::Interpreter;
+use crate::lox_callable::Arity;
 use crate::lox_function::LoxFunction;
 use crate::lox_instance::LoxInstance;
 use crate::lox_result::Result;
 use crate::object::Object;
+use crate::token::Token;
 use crate::unique_id::unique_u128;
 
 use std::collections::HashMap;
@@ -26,23 +28,60 @@ impl LoxClass {
         self.0.find_method(name)
     }
 
-    pub fn arity(&self) -> usize {
+    pub fn arity(&self) -> Arity {
         self.0.arity()
     }
 
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    pub fn superclass(&self) -> Option<LoxClass> {
+        self.0.superclass.clone()
+    }
+
+    /// The names of the class's methods, including inherited ones, sorted.
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.0.methods.keys().cloned().collect();
+        if let Some(superclass) = &self.0.superclass {
+            names.extend(superclass.method_names());
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Whether this class is `other` or inherits from it.
+    pub fn is_subclass_of(&self, other: &LoxClass) -> bool {
+        self == other
+            || self
+                .0
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.is_subclass_of(other))
+    }
+
     // We implement call here instead of in LoxClassInternal because we need
     // self to be Gc-wrapped, since we clone it.
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
+        paren: &Gc<Token>,
         arguments: &[Object],
+        named_arguments: &[(Gc<Token>, Object)],
     ) -> Result<Object> {
         let instance = LoxInstance::new(self.clone());
 
         if let Some(initializer) = self.find_method("init") {
-            initializer
-                .bind(instance.clone())
-                .call(interpreter, arguments)?;
+            initializer.bind(instance.clone()).call(
+                interpreter,
+                paren,
+                arguments,
+                named_arguments,
+            )?;
+        } else {
+            self.arity()
+                .check(paren, arguments.len() + named_arguments.len())?;
         }
 
         Ok(Object::Instance(instance))
@@ -97,11 +136,11 @@ impl LoxClassInternal {
         })
     }
 
-    fn arity(&self) -> usize {
+    fn arity(&self) -> Arity {
         if let Some(initializer) = self.find_method("init") {
             initializer.arity()
         } else {
-            0
+            Arity::exactly(0)
         }
     }
 
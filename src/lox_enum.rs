@@ -0,0 +1,144 @@
+use crate::interpreter::Interpreter;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_list::LoxList;
+use crate::lox_native::{NativeCall, NativeFunction};
+use crate::lox_result::Result;
+use crate::object::Object;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+use crate::unique_id::unique_u128;
+
+use std::fmt;
+
+use gc::{Finalize, Gc, Trace};
+
+/// An enum declared with `enum Color { Red, Green, Blue }`, which is a
+/// namespace for its variants.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct LoxEnum(Gc<LoxEnumInternal>);
+
+impl LoxEnum {
+    pub fn new(name: &str, variants: &[&str]) -> Self {
+        Self(Gc::new(LoxEnumInternal {
+            name: name.to_owned(),
+            variants: variants
+                .iter()
+                .enumerate()
+                .map(|(ordinal, variant)| LoxEnumValue::new(name, variant, ordinal))
+                .collect(),
+            id: unique_u128(),
+        }))
+    }
+
+    /// Look up one of the enum's variants, or its `values` method.
+    pub fn get(&self, name: &Token) -> Result<Object> {
+        if name.lexeme == "values" {
+            return Ok(Object::Callable(LoxCallable::Native(
+                NativeFunction::new("values", Arity::exactly(0), enum_values)
+                    .bind(Object::Enum(self.clone())),
+            )));
+        }
+        match self.0.variants.iter().find(|v| v.name() == name.lexeme) {
+            Some(variant) => Ok(Object::EnumValue(variant.clone())),
+            None => Err(RuntimeError::new(
+                name.clone().into(),
+                &format!(
+                    "Undefined variant '{}' of enum '{}'.",
+                    name.lexeme, self.0.name
+                ),
+            )
+            .into()),
+        }
+    }
+}
+
+impl fmt::Display for LoxEnum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.name)
+    }
+}
+
+impl PartialEq for LoxEnum {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id == other.0.id
+    }
+}
+
+#[derive(Clone, Debug, Finalize, Trace)]
+struct LoxEnumInternal {
+    name: String,
+    variants: Vec<LoxEnumValue>,
+    id: u128,
+}
+
+fn enum_values(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    if let Object::Enum(lox_enum) = call.this {
+        Ok(Object::List(LoxList::new(
+            lox_enum
+                .0
+                .variants
+                .iter()
+                .map(|v| Object::EnumValue(v.clone()))
+                .collect(),
+        )))
+    } else {
+        panic!("Expect 'values' to be bound to an enum.");
+    }
+}
+
+/// One variant of an enum. Each variant is a singleton, so variants are
+/// compared by identity.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct LoxEnumValue(Gc<LoxEnumValueInternal>);
+
+impl LoxEnumValue {
+    fn new(enum_name: &str, name: &str, ordinal: usize) -> Self {
+        Self(Gc::new(LoxEnumValueInternal {
+            enum_name: enum_name.to_owned(),
+            name: name.to_owned(),
+            ordinal,
+            id: unique_u128(),
+        }))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    pub fn id(&self) -> u128 {
+        self.0.id
+    }
+
+    /// Look up the variant's `name` or `ordinal`.
+    pub fn get(&self, name: &Token) -> Result<Object> {
+        match name.lexeme.as_str() {
+            "name" => Ok(Object::String(Gc::new(self.0.name.clone()))),
+            "ordinal" => Ok(Object::Integer(self.0.ordinal as i64)),
+            _ => Err(RuntimeError::new(
+                name.clone().into(),
+                &format!("Undefined property '{}'.", name.lexeme),
+            )
+            .into()),
+        }
+    }
+}
+
+impl fmt::Display for LoxEnumValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.0.enum_name, self.0.name)
+    }
+}
+
+impl PartialEq for LoxEnumValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id == other.0.id
+    }
+}
+
+#[derive(Clone, Debug, Finalize, Trace)]
+struct LoxEnumValueInternal {
+    enum_name: String,
+    name: String,
+    ordinal: usize,
+    id: u128,
+}
@@ -1,10 +1,15 @@
 use crate::environment::Environment;
 use crate::interpreter::Interpreter;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_generator::LoxGenerator;
 use crate::lox_instance::LoxInstance;
+use crate::lox_list::LoxList;
 use crate::lox_result::Result;
-use crate::lox_return::Return;
+use crate::lox_return::{Return, TailCall};
 use crate::object::Object;
+use crate::runtime_error::RuntimeError;
 use crate::stmt;
+use crate::token::Token;
 use crate::unique_id::unique_u128;
 
 use std::fmt;
@@ -40,18 +45,65 @@ impl LoxFunction {
         LoxFunction::new(self.declaration.clone(), environment, self.is_initializer)
     }
 
-    pub fn arity(&self) -> usize {
-        self.declaration.params.len()
+    pub fn name(&self) -> &str {
+        &self.declaration.name.lexeme
     }
 
+    pub fn arity(&self) -> Arity {
+        let params = &self.declaration.params;
+        let required = params.iter().filter(|p| p.default.is_none()).count();
+        if self.declaration.rest.is_some() {
+            Arity::at_least(required)
+        } else {
+            Arity::range(required, params.len())
+        }
+    }
+
+    /// Tail calls made by the function, and then by the functions they call,
+    /// are made here in a loop rather than by recursing.
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
+        paren: &Gc<Token>,
         arguments: &[Object],
+        named_arguments: &[(Gc<Token>, Object)],
     ) -> Result<Object> {
-        let environment = Environment::new(Some(self.closure.clone()));
-        for (param, arg) in zip(self.declaration.params.iter(), arguments.iter()) {
-            environment.define(&param.lexeme, arg.clone());
+        let mut result = self.call_once(interpreter, paren, arguments, named_arguments);
+        loop {
+            let tail_call = match result {
+                Err(error) => error.downcast::<TailCall>()?,
+                result => return result,
+            };
+            let TailCall {
+                callee,
+                paren,
+                arguments,
+                named_arguments,
+            } = *tail_call;
+            result = match &callee {
+                LoxCallable::Function(function) => {
+                    function.call_once(interpreter, &paren, &arguments, &named_arguments)
+                }
+                callee => callee.call(interpreter, &paren, &arguments, &named_arguments),
+            };
+        }
+    }
+
+    fn call_once(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Gc<Token>,
+        arguments: &[Object],
+        named_arguments: &[(Gc<Token>, Object)],
+    ) -> Result<Object> {
+        let environment =
+            self.bind_arguments(interpreter, paren, arguments, named_arguments)?;
+
+        if self.declaration.is_generator {
+            return Ok(Object::Generator(LoxGenerator::new(
+                &self.declaration,
+                environment,
+            )));
         }
 
         if let Err(err) = interpreter.execute_block(&self.declaration.body, environment) {
@@ -72,6 +124,76 @@ impl LoxFunction {
         Ok(Object::Nil)
     }
 
+    /// Create the environment for a call, with every parameter bound to an
+    /// argument or its default, and any extra positional arguments collected
+    /// into the rest parameter.
+    fn bind_arguments(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Gc<Token>,
+        arguments: &[Object],
+        named_arguments: &[(Gc<Token>, Object)],
+    ) -> Result<Environment> {
+        let params = &self.declaration.params;
+
+        if named_arguments.is_empty() || arguments.len() > params.len() {
+            self.arity().check(paren, arguments.len())?;
+        }
+
+        let mut values: Vec<Option<Object>> = arguments
+            .iter()
+            .take(params.len())
+            .cloned()
+            .map(Some)
+            .collect();
+        values.resize(params.len(), None);
+
+        for (name, value) in named_arguments {
+            match params.iter().position(|p| p.name.lexeme == name.lexeme) {
+                Some(i) if values[i].is_some() => {
+                    return Err(RuntimeError::new(
+                        name.clone(),
+                        &format!("Got multiple values for parameter '{}'.", name.lexeme),
+                    )
+                    .into())
+                }
+                Some(i) => values[i] = Some(value.clone()),
+                None => {
+                    return Err(RuntimeError::new(
+                        name.clone(),
+                        &format!("No parameter named '{}'.", name.lexeme),
+                    )
+                    .into())
+                }
+            }
+        }
+
+        let environment = Environment::new(Some(self.closure.clone()));
+        for (param, value) in zip(params.iter(), values) {
+            let value = match (value, &param.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => {
+                    interpreter.evaluate_in_environment(default, environment.clone())?
+                }
+                (None, None) => {
+                    return Err(RuntimeError::new(
+                        paren.clone(),
+                        &format!("Missing argument for parameter '{}'.", param.name.lexeme),
+                    )
+                    .into())
+                }
+            };
+            environment.define(&param.name.lexeme, value);
+        }
+
+        if let Some(rest) = &self.declaration.rest {
+            let extra = arguments.get(params.len()..).unwrap_or_default();
+            environment.define(&rest.lexeme, Object::List(LoxList::new(extra.to_vec())));
+        }
+
+        Ok(environment)
+    }
+
     pub fn id(&self) -> u128 {
         self.id
     }
@@ -0,0 +1,282 @@
+use crate::environment::Environment;
+use crate::interpreter::{is_truthy, Interpreter};
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_iterator::LoxIterator;
+use crate::lox_native::{NativeCall, NativeFn, NativeFunction};
+use crate::lox_result::Result;
+use crate::lox_return::Return;
+use crate::object::Object;
+use crate::runtime_error::RuntimeError;
+use crate::stmt::{self, Stmt};
+use crate::token::Token;
+
+use std::fmt;
+use std::mem;
+use std::slice;
+
+use gc::{Finalize, Gc, GcCell, Trace};
+
+/// The object returned by calling a function containing `yield`.
+///
+/// The interpreter runs statements by recursing on the Rust stack, so it
+/// can't suspend in the middle of one. Instead a generator keeps its own stack
+/// of frames for the statements it's in the middle of, and only steps through
+/// statements which themselves contain a `yield`. Everything else, including
+/// expressions (`yield` is a statement), runs to completion on the
+/// interpreter.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct LoxGenerator(Gc<GcCell<GeneratorState>>);
+
+#[derive(Debug, Finalize, Trace)]
+struct GeneratorState {
+    name: String,
+    // Empty once the generator has finished.
+    frames: Vec<Frame>,
+    // A value produced by `hasNext` which `next` hasn't returned yet.
+    peeked: Option<Object>,
+    running: bool,
+}
+
+#[derive(Debug, Finalize, Trace)]
+enum Frame {
+    /// A statement which hasn't started running yet.
+    Start(Stmt, Environment),
+    Block {
+        statements: Vec<Stmt>,
+        index: usize,
+        environment: Environment,
+    },
+    While(Gc<stmt::While>, Environment),
+    ForIn(Gc<stmt::ForIn>, LoxIterator, Environment),
+}
+
+impl LoxGenerator {
+    /// `environment` holds the function's parameters.
+    pub fn new(declaration: &stmt::Function, environment: Environment) -> Self {
+        Self(Gc::new(GcCell::new(GeneratorState {
+            name: declaration.name.lexeme.clone(),
+            frames: vec![Frame::Block {
+                statements: declaration.body.clone(),
+                index: 0,
+                environment,
+            }],
+            peeked: None,
+            running: false,
+        })))
+    }
+
+    /// Run the generator until its next `yield`, returning `None` once it has
+    /// finished.
+    pub fn next(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Gc<Token>,
+    ) -> Result<Option<Object>> {
+        if let Some(value) = self.0.borrow_mut().peeked.take() {
+            return Ok(Some(value));
+        }
+        self.resume(interpreter, token)
+    }
+
+    pub fn has_next(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Gc<Token>,
+    ) -> Result<bool> {
+        if self.0.borrow().peeked.is_some() {
+            return Ok(true);
+        }
+        let value = self.resume(interpreter, token)?;
+        let has_next = value.is_some();
+        self.0.borrow_mut().peeked = value;
+        Ok(has_next)
+    }
+
+    /// Look up one of the generator's methods.
+    pub fn get(&self, name: &Token) -> Result<Object> {
+        let (method, function): (&'static str, NativeFn) = match name.lexeme.as_str() {
+            "hasNext" => ("hasNext", generator_has_next),
+            "next" => ("next", generator_next),
+            _ => {
+                return Err(RuntimeError::new(
+                    name.clone().into(),
+                    &format!("Undefined property '{}'.", &name.lexeme),
+                )
+                .into())
+            }
+        };
+        Ok(Object::Callable(LoxCallable::Native(
+            NativeFunction::new(method, Arity::exactly(0), function)
+                .bind(Object::Generator(self.clone())),
+        )))
+    }
+
+    fn resume(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Gc<Token>,
+    ) -> Result<Option<Object>> {
+        // The frames are taken out of the generator while it runs, so that the
+        // generator's body can use the generator without a borrow conflict.
+        let mut frames = {
+            let mut state = self.0.borrow_mut();
+            if state.running {
+                return Err(RuntimeError::new(
+                    token.clone(),
+                    "Generator is already running.",
+                )
+                .into());
+            }
+            state.running = true;
+            mem::take(&mut state.frames)
+        };
+
+        let result = run(&mut frames, interpreter);
+
+        let mut state = self.0.borrow_mut();
+        state.running = false;
+        match result {
+            Ok(Some(value)) => {
+                state.frames = frames;
+                Ok(Some(value))
+            }
+            Ok(None) => Ok(None),
+            // A `return` finishes the generator.
+            Err(error) if error.downcast_ref::<Return>().is_some() => Ok(None),
+            // Errors finish the generator too, and propagate to whoever asked
+            // for the next value.
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Step through `frames` until a `yield` produces a value, or there are no
+/// frames left.
+fn run(frames: &mut Vec<Frame>, interpreter: &mut Interpreter) -> Result<Option<Object>> {
+    while let Some(frame) = frames.last_mut() {
+        let next = match frame {
+            Frame::Start(stmt, environment) => {
+                let (stmt, environment) = (stmt.clone(), environment.clone());
+                frames.pop();
+                if let Some(value) = start(stmt, environment, frames, interpreter)? {
+                    return Ok(Some(value));
+                }
+                continue;
+            }
+            Frame::Block {
+                statements,
+                index,
+                environment,
+            } => statements.get(*index).map(|stmt| {
+                *index += 1;
+                Frame::Start(stmt.clone(), environment.clone())
+            }),
+            Frame::While(stmt, environment) => {
+                let condition = interpreter
+                    .evaluate_in_environment(&stmt.condition, environment.clone())?;
+                is_truthy(&condition)
+                    .then(|| Frame::Start(stmt.body.clone(), environment.clone()))
+            }
+            Frame::ForIn(stmt, iterator, environment) => {
+                iterator.next(interpreter, &stmt.keyword)?.map(|value| {
+                    let iteration = Environment::new(Some(environment.clone()));
+                    iteration.define(&stmt.name.lexeme, value);
+                    Frame::Start(stmt.body.clone(), iteration)
+                })
+            }
+        };
+        // A frame with nothing left to run is finished.
+        match next {
+            Some(frame) => frames.push(frame),
+            None => {
+                frames.pop();
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Start running `stmt`, either to completion if it doesn't contain a `yield`,
+/// or by pushing frames for it. Returns the value of a `yield`.
+fn start(
+    stmt: Stmt,
+    environment: Environment,
+    frames: &mut Vec<Frame>,
+    interpreter: &mut Interpreter,
+) -> Result<Option<Object>> {
+    if !stmt.contains_yield() {
+        interpreter.execute_block(slice::from_ref(&stmt), environment)?;
+        return Ok(None);
+    }
+
+    match &stmt {
+        Stmt::Block(block) => frames.push(Frame::Block {
+            statements: block.statements.clone(),
+            index: 0,
+            environment: Environment::new(Some(environment)),
+        }),
+        Stmt::ForIn(for_in) => {
+            let iterable = interpreter
+                .evaluate_in_environment(&for_in.iterable, environment.clone())?;
+            let iterator = LoxIterator::new(&iterable, &for_in.keyword)?;
+            frames.push(Frame::ForIn(for_in.clone(), iterator, environment));
+        }
+        Stmt::If(if_) => {
+            let condition =
+                interpreter.evaluate_in_environment(&if_.condition, environment.clone())?;
+            if is_truthy(&condition) {
+                frames.push(Frame::Start(if_.then_branch.clone(), environment));
+            } else if let Some(else_branch) = &if_.else_branch {
+                frames.push(Frame::Start(else_branch.clone(), environment));
+            }
+        }
+        Stmt::Match(match_) => {
+            let (arm, environment) = interpreter
+                .in_environment(environment, |interpreter| {
+                    interpreter.select_match_arm(match_)
+                })?;
+            frames.push(Frame::Start(arm.body.clone(), environment));
+        }
+        Stmt::While(while_) => frames.push(Frame::While(while_.clone(), environment)),
+        Stmt::Yield(yield_) => {
+            let value = match &yield_.value {
+                Some(value) => interpreter.evaluate_in_environment(value, environment)?,
+                None => Object::Nil,
+            };
+            return Ok(Some(value));
+        }
+        _ => unreachable!("Only compound statements can contain a yield."),
+    }
+    Ok(None)
+}
+
+impl fmt::Display for LoxGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<generator {}>", self.0.borrow().name)
+    }
+}
+
+// Generators are compared by identity.
+impl PartialEq for LoxGenerator {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+fn this_generator(call: &NativeCall) -> LoxGenerator {
+    if let Object::Generator(generator) = call.this {
+        generator.clone()
+    } else {
+        panic!("Expect generator methods to be bound to a generator.");
+    }
+}
+
+fn generator_next(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let value = this_generator(call).next(interpreter, call.paren)?;
+    Ok(value.unwrap_or(Object::Nil))
+}
+
+fn generator_has_next(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let has_next = this_generator(call).has_next(interpreter, call.paren)?;
+    Ok(Object::Boolean(has_next))
+}
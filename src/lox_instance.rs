@@ -1,5 +1,6 @@
 use crate::lox_callable::LoxCallable;
 use crate::lox_class::LoxClass;
+use crate::lox_function::LoxFunction;
 use crate::lox_result::Result;
 use crate::object::Object;
 use crate::runtime_error::RuntimeError;
@@ -9,7 +10,7 @@ use std::{collections::HashMap, fmt};
 
 use gc::{Finalize, Gc, GcCell, Trace};
 
-#[derive(Clone, Debug, Finalize, PartialEq, Trace)]
+#[derive(Clone, Debug, Finalize, Trace)]
 pub struct LoxInstance {
     class: LoxClass,
     fields: Gc<GcCell<HashMap<String, Object>>>,
@@ -28,10 +29,8 @@ impl LoxInstance {
             return Ok(field.clone());
         }
 
-        if let Some(method) = self.class.find_method(&name.lexeme) {
-            return Ok(Object::Callable(LoxCallable::Function(
-                method.bind(self.clone()),
-            )));
+        if let Some(method) = self.find_method(&name.lexeme) {
+            return Ok(Object::Callable(LoxCallable::Function(method)));
         }
 
         Err(RuntimeError::new(
@@ -41,8 +40,57 @@ impl LoxInstance {
         .into())
     }
 
+    /// The address of the instance's fields, which identifies it.
+    pub fn address(&self) -> usize {
+        &*self.fields as *const _ as usize
+    }
+
+    pub fn class(&self) -> &LoxClass {
+        &self.class
+    }
+
+    pub fn field(&self, name: &str) -> Option<Object> {
+        self.fields.borrow().get(name).cloned()
+    }
+
+    /// Look up a method on this instance's class, ignoring fields, and bind it
+    /// to this instance.
+    pub fn find_method(&self, name: &str) -> Option<LoxFunction> {
+        self.class
+            .find_method(name)
+            .map(|method| method.bind(self.clone()))
+    }
+
+    /// The instance's fields, sorted by name.
+    pub fn fields(&self) -> Vec<(String, Object)> {
+        let mut fields: Vec<_> = self
+            .fields
+            .borrow()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        fields
+    }
+
     pub fn set(&self, name: &Token, value: Object) {
-        self.fields.borrow_mut().insert(name.lexeme.clone(), value);
+        self.set_field(&name.lexeme, value);
+    }
+
+    pub fn set_field(&self, name: &str, value: Object) {
+        self.fields.borrow_mut().insert(name.to_owned(), value);
+    }
+
+    /// Returns whether the instance had the field.
+    pub fn remove_field(&self, name: &str) -> bool {
+        self.fields.borrow_mut().remove(name).is_some()
+    }
+}
+
+// Instances are compared by identity, not by the contents of their fields.
+impl PartialEq for LoxInstance {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(&self.fields, &other.fields)
     }
 }
 
@@ -0,0 +1,48 @@
+use crate::interpreter::Interpreter;
+use crate::lox_generator::LoxGenerator;
+use crate::lox_list::LoxList;
+use crate::lox_result::Result;
+use crate::object::Object;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+
+use gc::{Finalize, Gc, Trace};
+
+/// The state of a `for (var x in iterable)` loop. Lists are iterated by index,
+/// so elements pushed during the loop are visited too. Maps are iterated over a
+/// snapshot of their keys.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub enum LoxIterator {
+    Generator(LoxGenerator),
+    List(LoxList, usize),
+}
+
+impl LoxIterator {
+    pub fn new(iterable: &Object, keyword: &Gc<Token>) -> Result<Self> {
+        match iterable {
+            Object::Generator(generator) => Ok(LoxIterator::Generator(generator.clone())),
+            Object::List(list) => Ok(LoxIterator::List(list.clone(), 0)),
+            Object::Map(map) => Ok(LoxIterator::List(map.keys(), 0)),
+            _ => Err(RuntimeError::new(
+                keyword.clone(),
+                "Can only iterate over lists, maps and generators.",
+            )
+            .into()),
+        }
+    }
+
+    pub fn next(
+        &mut self,
+        interpreter: &mut Interpreter,
+        keyword: &Gc<Token>,
+    ) -> Result<Option<Object>> {
+        match self {
+            LoxIterator::Generator(generator) => generator.next(interpreter, keyword),
+            LoxIterator::List(list, index) => {
+                let element = list.get_element(*index);
+                *index += 1;
+                Ok(element)
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_native::{define_native, NativeCall, NativeFn, NativeFunction};
+use crate::lox_result::Result;
+use crate::object::{display_container, Object};
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+
+use std::fmt;
+
+use gc::{Finalize, Gc, GcCell, Trace};
+
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct LoxList(Gc<GcCell<Vec<Object>>>);
+
+impl LoxList {
+    pub fn new(elements: Vec<Object>) -> Self {
+        Self(Gc::new(GcCell::new(elements)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn get_element(&self, index: usize) -> Option<Object> {
+        self.0.borrow().get(index).cloned()
+    }
+
+    pub fn elements(&self) -> Vec<Object> {
+        self.0.borrow().clone()
+    }
+
+    /// The address of the list's storage, which identifies it.
+    pub fn address(&self) -> usize {
+        &*self.0 as *const _ as usize
+    }
+
+    pub fn push(&self, value: Object) {
+        self.0.borrow_mut().push(value);
+    }
+
+    /// Look up one of the list's methods.
+    pub fn get(&self, name: &Token) -> Result<Object> {
+        let (method, arity, function): (&'static str, Arity, NativeFn) =
+            match name.lexeme.as_str() {
+                "get" => ("get", Arity::exactly(1), list_get),
+                "length" => ("length", Arity::exactly(0), list_length),
+                "pop" => ("pop", Arity::exactly(0), list_pop),
+                "push" => ("push", Arity::exactly(1), list_push),
+                "set" => ("set", Arity::exactly(2), list_set),
+                _ => {
+                    return Err(RuntimeError::new(
+                        name.clone().into(),
+                        &format!("Undefined property '{}'.", &name.lexeme),
+                    )
+                    .into())
+                }
+            };
+        Ok(Object::Callable(LoxCallable::Native(
+            NativeFunction::new(method, arity, function).bind(Object::List(self.clone())),
+        )))
+    }
+}
+
+impl fmt::Display for LoxList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display_container(f, self.address(), "[...]", |f| {
+            write!(f, "[")?;
+            for (i, element) in self.0.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{element}")?;
+            }
+            write!(f, "]")
+        })
+    }
+}
+
+// Lists are compared by identity, like instances.
+impl PartialEq for LoxList {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "List", Arity::at_least(0), list);
+}
+
+fn list(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::List(LoxList::new(call.arguments.to_vec())))
+}
+
+fn this_list(call: &NativeCall) -> LoxList {
+    if let Object::List(list) = call.this {
+        list.clone()
+    } else {
+        panic!("Expect list methods to be bound to a list.");
+    }
+}
+
+fn checked_index(call: &NativeCall, list: &LoxList) -> Result<usize> {
+    let index = call.index(0)?;
+    if index >= list.len() {
+        return Err(call.error("List index out of range."));
+    }
+    Ok(index)
+}
+
+fn list_get(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let list = this_list(call);
+    let index = checked_index(call, &list)?;
+    Ok(list.get_element(index).expect("Index was checked."))
+}
+
+fn list_length(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Integer(this_list(call).len() as i64))
+}
+
+fn list_pop(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let popped = this_list(call).0.borrow_mut().pop();
+    popped.ok_or_else(|| call.error("Can't pop from an empty list."))
+}
+
+fn list_push(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    this_list(call).push(call.arguments[0].clone());
+    Ok(Object::Nil)
+}
+
+fn list_set(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let list = this_list(call);
+    let index = checked_index(call, &list)?;
+    list.0.borrow_mut()[index] = call.arguments[1].clone();
+    Ok(call.arguments[1].clone())
+}
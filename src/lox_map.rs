@@ -0,0 +1,243 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_list::LoxList;
+use crate::lox_native::{define_native, NativeCall, NativeFn, NativeFunction};
+use crate::lox_result::Result;
+use crate::object::{display_container, Object};
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use gc::{Finalize, Gc, GcCell, Trace};
+
+/// A map from keys to values, which remembers the order keys were inserted in.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct LoxMap(Gc<GcCell<LoxMapInternal>>);
+
+impl LoxMap {
+    pub fn new() -> Self {
+        Self(Gc::new(GcCell::new(LoxMapInternal {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        })))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    pub fn get_value(&self, key: &MapKey) -> Option<Object> {
+        let map = self.0.borrow();
+        map.index.get(key).map(|&i| map.entries[i].value.clone())
+    }
+
+    pub fn insert(&self, key: MapKey, key_object: Object, value: Object) {
+        let mut map = self.0.borrow_mut();
+        match map.index.get(&key) {
+            Some(&i) => map.entries[i].value = value,
+            None => {
+                let i = map.entries.len();
+                map.entries.push(Entry {
+                    key: key_object,
+                    value,
+                });
+                map.index.insert(key, i);
+            }
+        }
+    }
+
+    pub fn remove(&self, key: &MapKey) -> Option<Object> {
+        let mut map = self.0.borrow_mut();
+        let i = map.index.remove(key)?;
+        let entry = map.entries.remove(i);
+        for index in map.index.values_mut() {
+            if *index > i {
+                *index -= 1;
+            }
+        }
+        Some(entry.value.clone())
+    }
+
+    pub fn entries(&self) -> Vec<(Object, Object)> {
+        let map = self.0.borrow();
+        map.entries
+            .iter()
+            .map(|e| (e.key.clone(), e.value.clone()))
+            .collect()
+    }
+
+    /// The address of the map's storage, which identifies it.
+    pub fn address(&self) -> usize {
+        &*self.0 as *const _ as usize
+    }
+
+    pub fn keys(&self) -> LoxList {
+        LoxList::new(
+            self.0
+                .borrow()
+                .entries
+                .iter()
+                .map(|e| e.key.clone())
+                .collect(),
+        )
+    }
+
+    /// Look up one of the map's methods.
+    pub fn get(&self, name: &Token) -> Result<Object> {
+        let (method, arity, function): (&'static str, Arity, NativeFn) =
+            match name.lexeme.as_str() {
+                "get" => ("get", Arity::exactly(1), map_get),
+                "has" => ("has", Arity::exactly(1), map_has),
+                "keys" => ("keys", Arity::exactly(0), map_keys),
+                "length" => ("length", Arity::exactly(0), map_length),
+                "remove" => ("remove", Arity::exactly(1), map_remove),
+                "set" => ("set", Arity::exactly(2), map_set),
+                "values" => ("values", Arity::exactly(0), map_values),
+                _ => {
+                    return Err(RuntimeError::new(
+                        name.clone().into(),
+                        &format!("Undefined property '{}'.", &name.lexeme),
+                    )
+                    .into())
+                }
+            };
+        Ok(Object::Callable(LoxCallable::Native(
+            NativeFunction::new(method, arity, function).bind(Object::Map(self.clone())),
+        )))
+    }
+}
+
+impl fmt::Display for LoxMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display_container(f, self.address(), "{...}", |f| {
+            write!(f, "{{")?;
+            for (i, entry) in self.0.borrow().entries.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: {}", entry.key, entry.value)?;
+            }
+            write!(f, "}}")
+        })
+    }
+}
+
+// Maps are compared by identity, like lists.
+impl PartialEq for LoxMap {
+    fn eq(&self, other: &Self) -> bool {
+        Gc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[derive(Debug, Finalize, Trace)]
+struct LoxMapInternal {
+    entries: Vec<Entry>,
+    // The position of each key in `entries`.
+    #[unsafe_ignore_trace]
+    index: HashMap<MapKey, usize>,
+}
+
+#[derive(Debug, Finalize, Trace)]
+struct Entry {
+    key: Object,
+    value: Object,
+}
+
+/// The hashable form of an object which can be used as a map key. Keys which
+/// are `==` in Lox have equal `MapKey`s.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Number(u64),
+    String(String),
+    EnumValue(u128),
+}
+
+impl MapKey {
+    pub fn new(key: &Object) -> Option<Self> {
+        match key {
+            Object::Nil => Some(MapKey::Nil),
+            Object::Boolean(b) => Some(MapKey::Boolean(*b)),
+            Object::Integer(n) => Some(MapKey::Integer(*n)),
+            // Whole floats are equal to the integer of the same value, and 0.0
+            // and -0.0 are equal but have different bits.
+            Object::Number(n)
+                if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n < i64::MAX as f64 =>
+            {
+                Some(MapKey::Integer(*n as i64))
+            }
+            Object::Number(n) if n.is_nan() => Some(MapKey::Number(f64::NAN.to_bits())),
+            Object::Number(n) => Some(MapKey::Number(n.to_bits())),
+            Object::String(s) => Some(MapKey::String(s.to_string())),
+            Object::EnumValue(v) => Some(MapKey::EnumValue(v.id())),
+            _ => None,
+        }
+    }
+}
+
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "Map", Arity::exactly(0), map);
+}
+
+fn map(_interpreter: &mut Interpreter, _call: &NativeCall) -> Result<Object> {
+    Ok(Object::Map(LoxMap::new()))
+}
+
+fn this_map(call: &NativeCall) -> LoxMap {
+    if let Object::Map(map) = call.this {
+        map.clone()
+    } else {
+        panic!("Expect map methods to be bound to a map.");
+    }
+}
+
+fn key(call: &NativeCall) -> Result<MapKey> {
+    MapKey::new(&call.arguments[0]).ok_or_else(|| {
+        call.error("Only nil, booleans, numbers, strings and enum values can be map keys.")
+    })
+}
+
+fn map_get(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(this_map(call).get_value(&key(call)?).unwrap_or(Object::Nil))
+}
+
+fn map_has(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Boolean(
+        this_map(call).get_value(&key(call)?).is_some(),
+    ))
+}
+
+fn map_keys(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::List(this_map(call).keys()))
+}
+
+fn map_length(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Integer(this_map(call).len() as i64))
+}
+
+fn map_remove(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(this_map(call).remove(&key(call)?).unwrap_or(Object::Nil))
+}
+
+fn map_set(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let key = key(call)?;
+    this_map(call).insert(key, call.arguments[0].clone(), call.arguments[1].clone());
+    Ok(call.arguments[1].clone())
+}
+
+fn map_values(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let map = this_map(call);
+    let values = map
+        .0
+        .borrow()
+        .entries
+        .iter()
+        .map(|e| e.value.clone())
+        .collect();
+    Ok(Object::List(LoxList::new(values)))
+}
@@ -0,0 +1,187 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_class::LoxClass;
+use crate::lox_instance::LoxInstance;
+use crate::lox_list::LoxList;
+use crate::lox_result::Result;
+use crate::object::Object;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+use crate::unique_id::unique_u128;
+
+use std::error::Error;
+use std::fmt;
+
+use gc::{Finalize, Gc, Trace};
+
+pub type NativeFn = fn(&mut Interpreter, &NativeCall) -> Result<Object>;
+
+/// A function implemented in Rust. Natives which are methods of a built-in
+/// object (e.g. a list's `push`) are bound to it, and get it as `this`.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct NativeFunction {
+    name: &'static str,
+    #[unsafe_ignore_trace]
+    arity: Arity,
+    #[unsafe_ignore_trace]
+    function: NativeFn,
+    this: Box<Object>,
+    id: u128,
+}
+
+impl NativeFunction {
+    pub fn new(name: &'static str, arity: Arity, function: NativeFn) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+            this: Box::new(Object::Nil),
+            id: unique_u128(),
+        }
+    }
+
+    pub fn bind(mut self, this: Object) -> Self {
+        self.this = Box::new(this);
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    /// Assumes the caller has already checked the arguments against the arity.
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Gc<Token>,
+        arguments: &[Object],
+    ) -> Result<Object> {
+        (self.function)(
+            interpreter,
+            &NativeCall {
+                name: self.name,
+                paren,
+                this: &self.this,
+                arguments,
+            },
+        )
+    }
+
+    pub fn id(&self) -> u128 {
+        self.id
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+/// Define a global native function.
+pub fn define_native(
+    globals: &Environment,
+    name: &'static str,
+    arity: Arity,
+    function: NativeFn,
+) {
+    globals.define(
+        name,
+        Object::Callable(LoxCallable::Native(NativeFunction::new(
+            name, arity, function,
+        ))),
+    );
+}
+
+/// The context a native function is called in, with helpers for checking the
+/// types of its arguments.
+pub struct NativeCall<'a> {
+    pub name: &'static str,
+    pub paren: &'a Gc<Token>,
+    pub this: &'a Object,
+    pub arguments: &'a [Object],
+}
+
+impl NativeCall<'_> {
+    /// A runtime error reported at the call site.
+    pub fn error(&self, message: &str) -> Box<dyn Error> {
+        RuntimeError::new(self.paren.clone(), message).into()
+    }
+
+    fn type_error(&self, index: usize, expected: &str) -> Box<dyn Error> {
+        self.error(&format!(
+            "Argument {} to '{}' must be {expected}.",
+            index + 1,
+            self.name
+        ))
+    }
+
+    pub fn string(&self, index: usize) -> Result<Gc<String>> {
+        match &self.arguments[index] {
+            Object::String(s) => Ok(s.clone()),
+            _ => Err(self.type_error(index, "a string")),
+        }
+    }
+
+    pub fn list(&self, index: usize) -> Result<LoxList> {
+        match &self.arguments[index] {
+            Object::List(list) => Ok(list.clone()),
+            _ => Err(self.type_error(index, "a list")),
+        }
+    }
+
+    /// A function or class, to call back.
+    pub fn callable(&self, index: usize) -> Result<LoxCallable> {
+        match &self.arguments[index] {
+            Object::Callable(callable) => Ok(callable.clone()),
+            Object::Class(class) => Ok(LoxCallable::Class(class.clone())),
+            _ => Err(self.type_error(index, "a function")),
+        }
+    }
+
+    pub fn instance(&self, index: usize) -> Result<LoxInstance> {
+        match &self.arguments[index] {
+            Object::Instance(instance) => Ok(instance.clone()),
+            _ => Err(self.type_error(index, "an instance")),
+        }
+    }
+
+    pub fn class(&self, index: usize) -> Result<LoxClass> {
+        match &self.arguments[index] {
+            Object::Class(class) | Object::Callable(LoxCallable::Class(class)) => {
+                Ok(class.clone())
+            }
+            _ => Err(self.type_error(index, "a class")),
+        }
+    }
+
+    /// Any number, with integers converted to floats.
+    pub fn number(&self, index: usize) -> Result<f64> {
+        match &self.arguments[index] {
+            Object::Integer(x) => Ok(*x as f64),
+            Object::Number(x) => Ok(*x),
+            _ => Err(self.type_error(index, "a number")),
+        }
+    }
+
+    pub fn integer(&self, index: usize) -> Result<i64> {
+        match &self.arguments[index] {
+            Object::Integer(x) => Ok(*x),
+            _ => Err(self.type_error(index, "an integer")),
+        }
+    }
+
+    /// A non-negative whole number, suitable for indexing.
+    pub fn index(&self, index: usize) -> Result<usize> {
+        match &self.arguments[index] {
+            Object::Integer(x) if *x >= 0 => Ok(*x as usize),
+            Object::Number(x) if *x >= 0.0 && x.fract() == 0.0 => Ok(*x as usize),
+            _ => Err(self.type_error(index, "a non-negative integer")),
+        }
+    }
+}
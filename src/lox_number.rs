@@ -0,0 +1,134 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::Arity;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::Object;
+
+use gc::Gc;
+
+const MAX_DIGITS: usize = 100;
+
+/// Format a float the way the Java reference implementation prints it: Java's
+/// `Double.toString`, which uses scientific notation for very large and small
+/// numbers, without the `.0` of whole numbers.
+pub fn java_format(x: f64) -> String {
+    let text = if x.is_nan() {
+        "NaN".to_owned()
+    } else if x.is_infinite() {
+        if x > 0.0 { "Infinity" } else { "-Infinity" }.to_owned()
+    } else if x == 0.0 || (1e-3..1e7).contains(&x.abs()) {
+        with_decimal_point(&x.to_string())
+    } else {
+        let scientific = format!("{x:e}");
+        let (mantissa, exponent) = scientific
+            .split_once('e')
+            .expect("Expect scientific notation to have an exponent.");
+        format!("{}E{exponent}", with_decimal_point(mantissa))
+    };
+    match text.strip_suffix(".0") {
+        Some(text) => text.to_owned(),
+        None => text,
+    }
+}
+
+fn with_decimal_point(digits: &str) -> String {
+    if digits.contains('.') {
+        digits.to_owned()
+    } else {
+        format!("{digits}.0")
+    }
+}
+
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "toFixed", Arity::exactly(2), to_fixed);
+    define_native(globals, "toPrecision", Arity::exactly(2), to_precision);
+    define_native(globals, "toRadix", Arity::exactly(2), to_radix);
+}
+
+fn string(text: String) -> Result<Object> {
+    Ok(Object::String(Gc::new(text)))
+}
+
+/// `toFixed(x, digits)` formats `x` with `digits` digits after the decimal
+/// point.
+fn to_fixed(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let x = call.number(0)?;
+    let digits = call.index(1)?;
+    if digits > MAX_DIGITS {
+        return Err(call.error(&format!("Can't format more than {MAX_DIGITS} digits.")));
+    }
+    string(format!("{x:.digits$}"))
+}
+
+/// `toPrecision(x, digits)` formats `x` with `digits` significant digits,
+/// switching to scientific notation when the exponent is too large or small
+/// to write them out.
+fn to_precision(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let x = call.number(0)?;
+    let digits = call.index(1)?;
+    if !(1..=MAX_DIGITS).contains(&digits) {
+        return Err(call.error(&format!("Precision must be between 1 and {MAX_DIGITS}.")));
+    }
+    if !x.is_finite() {
+        return string(x.to_string());
+    }
+
+    // Rounding to the precision can change the exponent, so take it from the
+    // rounded number.
+    let scientific = format!("{x:.*e}", digits - 1);
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("Expect scientific notation to have an exponent.");
+    let exponent: i64 = exponent.parse()?;
+
+    if exponent < -6 || exponent >= digits as i64 {
+        let sign = if exponent < 0 { "" } else { "+" };
+        string(format!("{mantissa}e{sign}{exponent}"))
+    } else {
+        let decimals = (digits as i64 - 1 - exponent) as usize;
+        string(format!("{x:.decimals$}"))
+    }
+}
+
+/// `toRadix(n, radix)` writes the integer `n` in a base between 2 and 36.
+fn to_radix(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let n = call.integer(0)?;
+    let radix = call.index(1)?;
+    if !(2..=36).contains(&radix) {
+        return Err(call.error("Radix must be between 2 and 36."));
+    }
+
+    let mut magnitude = n.unsigned_abs();
+    let mut digits = Vec::new();
+    loop {
+        let digit = (magnitude % radix as u64) as u32;
+        digits.push(char::from_digit(digit, radix as u32).expect("Expect a digit."));
+        magnitude /= radix as u64;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if n < 0 {
+        digits.push('-');
+    }
+    string(digits.iter().rev().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn java_formatting() {
+        assert_eq!(java_format(1.0), "1");
+        assert_eq!(java_format(-0.0), "-0");
+        assert_eq!(java_format(2.5), "2.5");
+        assert_eq!(java_format(1e21), "1.0E21");
+        assert_eq!(java_format(1.5e-5), "1.5E-5");
+        assert_eq!(java_format(12345678.0), "1.2345678E7");
+        assert_eq!(java_format(0.001), "0.001");
+        assert_eq!(java_format(f64::NAN), "NaN");
+        assert_eq!(java_format(f64::NEG_INFINITY), "-Infinity");
+    }
+}
@@ -1,8 +1,12 @@
+use crate::lox_callable::LoxCallable;
 use crate::object::Object;
+use crate::token::Token;
 
 use std::error::Error;
 use std::fmt::{self, Display};
 
+use gc::Gc;
+
 #[derive(Debug)]
 pub struct Return {
     pub value: Object,
@@ -21,3 +25,39 @@ impl Display for Return {
 }
 
 impl Error for Return {}
+
+/// A call in tail position. Rather than making it, the calling function
+/// unwinds and leaves the call to its `LoxFunction::call`, so that tail calls
+/// don't grow the stack.
+#[derive(Debug)]
+pub struct TailCall {
+    pub callee: LoxCallable,
+    pub paren: Gc<Token>,
+    pub arguments: Vec<Object>,
+    pub named_arguments: Vec<(Gc<Token>, Object)>,
+}
+
+impl Display for TailCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TailCall<{}>", self.callee)
+    }
+}
+
+impl Error for TailCall {}
+
+/// A request from the script to stop, with a status for the process. It
+/// unwinds the whole interpreter like an error, but isn't reported as one.
+/// `paren` is the closing parenthesis of the call to `exit`.
+#[derive(Debug)]
+pub struct Exit {
+    pub code: i32,
+    pub paren: Gc<Token>,
+}
+
+impl Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Exit<{}>", self.code)
+    }
+}
+
+impl Error for Exit {}
@@ -0,0 +1,48 @@
+use crate::lox_function::LoxFunction;
+use crate::unique_id::unique_u128;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use gc::{Finalize, Gc, Trace};
+
+/// A named set of methods which classes can mix in with `with`.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct LoxTrait(Gc<LoxTraitInternal>);
+
+impl LoxTrait {
+    pub fn new(name: &str, methods: HashMap<String, LoxFunction>) -> Self {
+        Self(Gc::new(LoxTraitInternal {
+            name: name.to_owned(),
+            methods,
+            id: unique_u128(),
+        }))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    pub fn methods(&self) -> &HashMap<String, LoxFunction> {
+        &self.0.methods
+    }
+}
+
+impl fmt::Display for LoxTrait {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.name)
+    }
+}
+
+impl PartialEq for LoxTrait {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id == other.0.id
+    }
+}
+
+#[derive(Clone, Debug, Finalize, Trace)]
+struct LoxTraitInternal {
+    name: String,
+    methods: HashMap<String, LoxFunction>,
+    id: u128,
+}
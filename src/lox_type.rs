@@ -0,0 +1,67 @@
+use crate::environment::Environment;
+use crate::lox_callable::LoxCallable;
+use crate::object::Object;
+
+use std::fmt;
+
+/// A kind of built-in value, which the `is` operator can test for, as in
+/// `x is Number`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BuiltinType {
+    Bool,
+    Class,
+    Function,
+    Integer,
+    Nil,
+    Number,
+    String,
+}
+
+impl BuiltinType {
+    const ALL: [BuiltinType; 7] = [
+        BuiltinType::Bool,
+        BuiltinType::Class,
+        BuiltinType::Function,
+        BuiltinType::Integer,
+        BuiltinType::Nil,
+        BuiltinType::Number,
+        BuiltinType::String,
+    ];
+
+    /// Whether `value` is of this type. Integers are numbers too.
+    pub fn matches(&self, value: &Object) -> bool {
+        match self {
+            BuiltinType::Bool => matches!(value, Object::Boolean(_)),
+            BuiltinType::Class => {
+                matches!(
+                    value,
+                    Object::Class(_) | Object::Callable(LoxCallable::Class(_))
+                )
+            }
+            BuiltinType::Function => matches!(
+                value,
+                Object::Callable(
+                    LoxCallable::Clock(_)
+                        | LoxCallable::Function(_)
+                        | LoxCallable::Native(_)
+                )
+            ),
+            BuiltinType::Integer => matches!(value, Object::Integer(_)),
+            BuiltinType::Nil => matches!(value, Object::Nil),
+            BuiltinType::Number => value.is_number(),
+            BuiltinType::String => matches!(value, Object::String(_)),
+        }
+    }
+}
+
+impl fmt::Display for BuiltinType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+pub fn define_globals(globals: &Environment) {
+    for type_ in BuiltinType::ALL {
+        globals.define(&type_.to_string(), Object::Type(type_));
+    }
+}
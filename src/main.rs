@@ -1,25 +1,52 @@
+#![allow(non_local_definitions)]
+
 mod environment;
 mod expr;
 mod generate_ast;
 mod interpreter;
+mod isolate;
 mod lox;
 mod lox_callable;
+mod lox_channel;
 mod lox_class;
+mod lox_enum;
 mod lox_function;
+mod lox_generator;
 mod lox_instance;
+mod lox_iterator;
+mod lox_list;
+mod lox_map;
+mod lox_native;
+mod lox_number;
 mod lox_result;
 mod lox_return;
+mod lox_trait;
+mod lox_type;
+mod native_assert;
+mod native_collections;
+mod native_format;
+mod native_fs;
+mod native_io;
+mod native_json;
+mod native_math;
+mod native_os;
+mod native_string;
+mod native_time;
 mod object;
 mod parser;
 mod pretty_printer;
+mod reflection;
 mod resolver;
 mod runtime_error;
 mod scanner;
+mod scheduler;
 mod stmt;
+mod test_runner;
 mod token;
 mod token_type;
 mod unique_id;
 
+use crate::interpreter::InterpreterOptions;
 use crate::lox::Lox;
 use crate::lox_result::Result;
 
@@ -27,17 +54,42 @@ use std::env;
 use std::process;
 
 fn main() -> Result<()> {
-    let mut lox = Lox::new();
-    let args: Vec<_> = env::args().collect();
-
-    match args.len() {
-        1 => lox.run_prompt()?,
-        2 => lox.run_file(&args[1])?,
-        _ => {
-            eprintln!("Usage: lox-tw [script]");
-            process::exit(64);
+    let mut options = InterpreterOptions::default();
+    let mut args = env::args().skip(1).peekable();
+    // Options come before the script. Everything after it is the script's.
+    while let Some(arg) = args.next_if(|arg| arg.starts_with("--")) {
+        match arg.as_str() {
+            "--java-numbers" => options.java_number_format = true,
+            "--no-file-access" => options.file_access = false,
+            _ => usage(),
+        }
+    }
+    // A script called `test` can still be run as `./test`.
+    if args.next_if_eq("test").is_some() {
+        // Every remaining argument is a file of tests.
+        let paths: Vec<_> = args.collect();
+        if paths.is_empty() {
+            usage();
+        }
+        if !test_runner::run_tests(&paths, &options) {
+            process::exit(1);
         }
+        return Ok(());
+    }
+    let script = args.next();
+    options.args = args.collect();
+    let mut lox = Lox::with_options(options);
+
+    match script {
+        None => lox.run_prompt()?,
+        Some(script) => lox.run_file(&script)?,
     }
 
     Ok(())
 }
+
+fn usage() -> ! {
+    eprintln!("Usage: lox-tw [--java-numbers] [--no-file-access] [script [args...]]");
+    eprintln!("       lox-tw [--java-numbers] [--no-file-access] test file...");
+    process::exit(64);
+}
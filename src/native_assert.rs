@@ -0,0 +1,56 @@
+use crate::environment::Environment;
+use crate::interpreter::{is_truthy, Interpreter};
+use crate::lox_callable::Arity;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::native_format::Repr;
+use crate::object::Object;
+use crate::runtime_error::RuntimeError;
+
+use gc::Gc;
+
+/// Natives for checking a script's behaviour, mainly in tests run with
+/// `lox-tw test`. A failed assertion is a runtime error.
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "assert", Arity::range(1, 2), assert);
+    define_native(globals, "assertEqual", Arity::range(2, 3), assert_equal);
+    define_native(globals, "assertThrows", Arity::exactly(1), assert_throws);
+}
+
+/// `assert(condition, message)`
+fn assert(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    if is_truthy(&call.arguments[0]) {
+        return Ok(Object::Nil);
+    }
+    match call.arguments.get(1) {
+        Some(_) => Err(call.error(&format!("Assertion failed: {}", call.string(1)?))),
+        None => Err(call.error("Assertion failed.")),
+    }
+}
+
+/// `assertEqual(actual, expected, message)` compares with `==`.
+fn assert_equal(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let actual = &call.arguments[0];
+    let expected = &call.arguments[1];
+    if interpreter.equals(call.paren, actual, expected)? {
+        return Ok(Object::Nil);
+    }
+    let difference = format!("Expected {} but got {}.", Repr(expected), Repr(actual));
+    match call.arguments.get(2) {
+        Some(_) => Err(call.error(&format!("{}: {difference}", call.string(2)?))),
+        None => Err(call.error(&difference)),
+    }
+}
+
+/// `assertThrows(function)` calls `function`, which must raise a runtime
+/// error, and returns the error's message.
+fn assert_throws(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let function = call.callable(0)?;
+    let error = match function.call(interpreter, call.paren, &[], &[]) {
+        Ok(_) => return Err(call.error("Expected the function to raise an error.")),
+        Err(error) => error,
+    };
+    // Anything else unwinding, such as a call to `exit`, carries on.
+    let error = error.downcast::<RuntimeError>()?;
+    Ok(Object::String(Gc::new(error.message)))
+}
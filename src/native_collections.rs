@@ -0,0 +1,163 @@
+use crate::environment::Environment;
+use crate::interpreter::{is_truthy, Interpreter};
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_list::LoxList;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::{compare_numbers, Object};
+
+use std::cmp::Ordering;
+use std::slice;
+
+/// Higher-order functions over lists. They call back into Lox for each
+/// element of a copy of the list, so callbacks can safely change the list, and
+/// errors in callbacks propagate out of them unchanged.
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "all", Arity::exactly(2), all);
+    define_native(globals, "any", Arity::exactly(2), any);
+    define_native(globals, "filter", Arity::exactly(2), filter);
+    define_native(globals, "forEach", Arity::exactly(2), for_each);
+    define_native(globals, "map", Arity::exactly(2), map);
+    define_native(globals, "reduce", Arity::range(2, 3), reduce);
+    define_native(globals, "sort", Arity::range(1, 2), sort);
+}
+
+fn call_back(
+    interpreter: &mut Interpreter,
+    call: &NativeCall,
+    function: &LoxCallable,
+    arguments: &[Object],
+) -> Result<Object> {
+    function.call(interpreter, call.paren, arguments, &[])
+}
+
+/// Call `predicate` on elements until it returns `stop_at`, returning
+/// whether it did.
+fn find(interpreter: &mut Interpreter, call: &NativeCall, stop_at: bool) -> Result<bool> {
+    let predicate = call.callable(1)?;
+    for element in call.list(0)?.elements() {
+        if is_truthy(&call_back(interpreter, call, &predicate, &[element])?) == stop_at {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// `all(list, predicate)` is whether `predicate` is true for every element.
+fn all(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Boolean(!find(interpreter, call, false)?))
+}
+
+/// `any(list, predicate)` is whether `predicate` is true for some element.
+fn any(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Boolean(find(interpreter, call, true)?))
+}
+
+fn filter(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let predicate = call.callable(1)?;
+    let mut kept = Vec::new();
+    for element in call.list(0)?.elements() {
+        let keep = call_back(interpreter, call, &predicate, slice::from_ref(&element))?;
+        if is_truthy(&keep) {
+            kept.push(element);
+        }
+    }
+    Ok(Object::List(LoxList::new(kept)))
+}
+
+fn for_each(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let function = call.callable(1)?;
+    for element in call.list(0)?.elements() {
+        call_back(interpreter, call, &function, &[element])?;
+    }
+    Ok(Object::Nil)
+}
+
+fn map(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let function = call.callable(1)?;
+    let mapped = call
+        .list(0)?
+        .elements()
+        .into_iter()
+        .map(|element| call_back(interpreter, call, &function, &[element]))
+        .collect::<Result<_>>()?;
+    Ok(Object::List(LoxList::new(mapped)))
+}
+
+/// `reduce(list, function, initial)` folds the list into one value by calling
+/// `function(accumulator, element)`. Without `initial`, the first element is
+/// the initial value.
+fn reduce(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let function = call.callable(1)?;
+    let mut elements = call.list(0)?.elements().into_iter();
+    let mut accumulator = match call.arguments.get(2) {
+        Some(initial) => initial.clone(),
+        None => elements.next().ok_or_else(|| {
+            call.error("Can't reduce an empty list without an initial value.")
+        })?,
+    };
+    for element in elements {
+        accumulator = call_back(interpreter, call, &function, &[accumulator, element])?;
+    }
+    Ok(accumulator)
+}
+
+/// `sort(list, compare)` returns a sorted copy of the list. `compare(a, b)`
+/// returns a negative number if `a` goes before `b`, a positive one if it goes
+/// after, and 0 if either order will do. Without it, numbers and strings sort
+/// in ascending order. The sort is stable.
+fn sort(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let elements = call.list(0)?.elements();
+    let compare = match call.arguments.get(1) {
+        Some(_) => Some(call.callable(1)?),
+        None => None,
+    };
+    let mut compare = |a: &Object, b: &Object| match &compare {
+        Some(compare) => {
+            let result = call_back(interpreter, call, compare, &[a.clone(), b.clone()])?;
+            match result {
+                Object::Integer(n) => Ok(n.cmp(&0)),
+                Object::Number(x) if !x.is_nan() => Ok(x.total_cmp(&0.0)),
+                _ => Err(call.error("Comparison function must return a number.")),
+            }
+        }
+        None => match (a, b) {
+            (Object::String(a), Object::String(b)) => Ok(a.cmp(b)),
+            _ => compare_numbers(a, b)
+                .ok_or_else(|| call.error(&format!("Can't compare '{a}' and '{b}'."))),
+        },
+    };
+    Ok(Object::List(LoxList::new(merge_sort(
+        elements,
+        &mut compare,
+    )?)))
+}
+
+/// A stable merge sort with a comparison which can fail, unlike the standard
+/// library's sorts.
+fn merge_sort<F>(mut elements: Vec<Object>, compare: &mut F) -> Result<Vec<Object>>
+where
+    F: FnMut(&Object, &Object) -> Result<Ordering>,
+{
+    if elements.len() <= 1 {
+        return Ok(elements);
+    }
+    let right = elements.split_off(elements.len() / 2);
+    let left = merge_sort(elements, compare)?;
+    let right = merge_sort(right, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // Taking from the left on ties keeps the sort stable.
+        if compare(l, r)? == Ordering::Greater {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
@@ -0,0 +1,256 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::Arity;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::{display_container, Object};
+
+use std::fmt;
+use std::iter::{self, Peekable};
+use std::str::Chars;
+
+use gc::Gc;
+
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "format", Arity::at_least(1), format);
+}
+
+/// `format(template, args...)` replaces each placeholder in `template` with an
+/// argument, displayed as `print` displays it. A placeholder is `{}` for the
+/// next argument, or `{n}` for the argument at index `n`, optionally followed
+/// by a specifier after a colon:
+///
+/// `{:[[fill]align][0][width][.precision][?]}`
+///
+/// - `align` is `<`, `^` or `>`, padding with `fill` (a space by default) up to
+///   `width` characters. Numbers are aligned right by default, and anything
+///   else left.
+/// - `0` pads numbers with zeros after their sign.
+/// - `precision` is the number of decimal places for numbers, or the maximum
+///   number of characters for other values.
+/// - `?` writes strings quoted and escaped, including those in lists and maps.
+///
+/// `{{` and `}}` are literal braces.
+fn format(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let template = call.string(0)?;
+    let arguments = &call.arguments[1..];
+    let mut chars = template.chars().peekable();
+    let mut text = String::new();
+    let mut next_argument = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.next_if_eq(&'{').is_some() => text.push('{'),
+            '}' if chars.next_if_eq(&'}').is_some() => text.push('}'),
+            '}' => return Err(call.error("Unmatched '}' in format template.")),
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(call.error("Unmatched '{' in format template.")),
+                    }
+                }
+                let (index, spec) = match placeholder.split_once(':') {
+                    Some((index, spec)) => (index, Some(spec)),
+                    None => (placeholder.as_str(), None),
+                };
+                let index = if index.is_empty() {
+                    next_argument += 1;
+                    next_argument - 1
+                } else {
+                    index.parse().map_err(|_| {
+                        call.error(&format!(
+                            "Invalid format placeholder '{{{placeholder}}}'."
+                        ))
+                    })?
+                };
+                let argument = arguments.get(index).ok_or_else(|| {
+                    call.error(&format!("Format argument {index} is out of range."))
+                })?;
+                let spec = match spec {
+                    Some(spec) => Spec::parse(spec).ok_or_else(|| {
+                        call.error(&format!("Invalid format specifier '{spec}'."))
+                    })?,
+                    None => Spec::default(),
+                };
+                spec.write(interpreter, call, &mut text, argument)?;
+            }
+            c => text.push(c),
+        }
+    }
+    Ok(Object::String(Gc::new(text)))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '<' => Some(Align::Left),
+            '^' => Some(Align::Center),
+            '>' => Some(Align::Right),
+            _ => None,
+        }
+    }
+}
+
+struct Spec {
+    fill: char,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    debug: bool,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self {
+            fill: ' ',
+            align: None,
+            zero: false,
+            width: 0,
+            precision: None,
+            debug: false,
+        }
+    }
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Self> {
+        let mut result = Self::default();
+        let mut chars = spec.chars().peekable();
+
+        let mut lookahead = spec.chars();
+        match (
+            lookahead.next(),
+            lookahead.next().and_then(Align::from_char),
+        ) {
+            (Some(fill), Some(align)) => {
+                result.fill = fill;
+                result.align = Some(align);
+                chars.nth(1);
+            }
+            (Some(c), None) => {
+                result.align = Align::from_char(c);
+                if result.align.is_some() {
+                    chars.next();
+                }
+            }
+            (None, _) => {}
+        }
+
+        result.zero = chars.next_if_eq(&'0').is_some();
+        if chars.peek().is_some_and(char::is_ascii_digit) {
+            result.width = number(&mut chars)?;
+        }
+        if chars.next_if_eq(&'.').is_some() {
+            result.precision = Some(number(&mut chars)?);
+        }
+        result.debug = chars.next_if_eq(&'?').is_some();
+        chars.next().is_none().then_some(result)
+    }
+
+    /// Write `argument` to `text`, displayed as `print` would display it
+    /// unless the spec says otherwise.
+    fn write(
+        &self,
+        interpreter: &mut Interpreter,
+        call: &NativeCall,
+        text: &mut String,
+        argument: &Object,
+    ) -> Result<()> {
+        let mut value = match (argument, self.precision) {
+            _ if self.debug => Repr(argument).to_string(),
+            // Integers are written exactly, rather than as the nearest float.
+            (Object::Integer(n), Some(0)) => n.to_string(),
+            (Object::Integer(n), Some(precision)) => {
+                format!("{n}.{}", "0".repeat(precision))
+            }
+            (Object::Number(x), Some(precision)) => format!("{x:.precision$}"),
+            (_, Some(precision)) => interpreter
+                .stringify(call.paren, argument)?
+                .chars()
+                .take(precision)
+                .collect(),
+            (_, None) => interpreter.stringify(call.paren, argument)?,
+        };
+
+        let padding = self.width.saturating_sub(value.chars().count());
+        if padding == 0 {
+            text.push_str(&value);
+            return Ok(());
+        }
+        if self.zero && self.align.is_none() && argument.is_number() {
+            // Zeros go between the sign and the digits.
+            let sign = if value.starts_with('-') { "-" } else { "" };
+            value.insert_str(sign.len(), &"0".repeat(padding));
+            text.push_str(&value);
+            return Ok(());
+        }
+
+        let default_align = if argument.is_number() {
+            Align::Right
+        } else {
+            Align::Left
+        };
+        let (before, after) = match self.align.unwrap_or(default_align) {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+        text.extend(iter::repeat_n(self.fill, before));
+        text.push_str(&value);
+        text.extend(iter::repeat_n(self.fill, after));
+        Ok(())
+    }
+}
+
+/// A width or precision. They're limited to what Rust's formatting supports,
+/// which is plenty and keeps padding from exhausting memory.
+fn number(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits.parse::<u16>().ok().map(usize::from)
+}
+
+/// Displays a value with strings quoted and escaped, so that they can be told
+/// apart from other values, and where they start and end is clear.
+pub struct Repr<'a>(pub &'a Object);
+
+impl fmt::Display for Repr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Object::String(s) => write!(f, "{:?}", s.as_str()),
+            Object::List(list) => display_container(f, list.address(), "[...]", |f| {
+                write!(f, "[")?;
+                for (i, element) in list.elements().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", Repr(element))?;
+                }
+                write!(f, "]")
+            }),
+            Object::Map(map) => display_container(f, map.address(), "{...}", |f| {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.entries().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", Repr(key), Repr(value))?;
+                }
+                write!(f, "}}")
+            }),
+            object => write!(f, "{object}"),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::Arity;
+use crate::lox_list::LoxList;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::Object;
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use gc::Gc;
+
+/// File system natives. Paths are relative to the working directory, and I/O
+/// failures are runtime errors.
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "appendFile", Arity::exactly(2), append_file);
+    define_native(globals, "createDir", Arity::exactly(1), create_dir);
+    define_native(globals, "fileExists", Arity::exactly(1), file_exists);
+    define_native(globals, "isDir", Arity::exactly(1), is_dir);
+    define_native(globals, "listDir", Arity::exactly(1), list_dir);
+    define_native(globals, "readFile", Arity::exactly(1), read_file);
+    define_native(globals, "readLines", Arity::exactly(1), read_lines);
+    define_native(globals, "removeDir", Arity::exactly(1), remove_dir);
+    define_native(globals, "removeFile", Arity::exactly(1), remove_file);
+    define_native(globals, "writeFile", Arity::exactly(2), write_file);
+}
+
+fn string(text: String) -> Object {
+    Object::String(Gc::new(text))
+}
+
+/// The path argument of a file system native, if file access is allowed.
+pub fn path(interpreter: &Interpreter, call: &NativeCall) -> Result<Gc<String>> {
+    if !interpreter.options().file_access {
+        return Err(call.error("File access is disabled."));
+    }
+    call.string(0)
+}
+
+/// Run `f`, turning an I/O error into a runtime error which says what couldn't
+/// be done to `path`.
+fn io<T>(
+    call: &NativeCall,
+    action: &str,
+    path: &str,
+    f: impl FnOnce() -> io::Result<T>,
+) -> Result<T> {
+    f().map_err(|error| call.error(&format!("Can't {action} '{path}': {error}.")))
+}
+
+fn append_file(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    let text = call.string(1)?;
+    io(call, "append to", &path, || {
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&*path)?
+            .write_all(text.as_bytes())
+    })?;
+    Ok(Object::Nil)
+}
+
+/// `createDir(path)` creates the directory along with any missing parents.
+fn create_dir(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    io(call, "create", &path, || fs::create_dir_all(&*path))?;
+    Ok(Object::Nil)
+}
+
+/// `fileExists(path)` is true for directories too.
+fn file_exists(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    Ok(Object::Boolean(Path::new(&*path).exists()))
+}
+
+fn is_dir(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    Ok(Object::Boolean(Path::new(&*path).is_dir()))
+}
+
+/// `listDir(path)` lists the names of the directory's entries, sorted.
+fn list_dir(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    let mut names = io(call, "list", &path, || {
+        fs::read_dir(&*path)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<io::Result<Vec<_>>>()
+    })?;
+    names.sort();
+    Ok(Object::List(LoxList::new(
+        names.into_iter().map(string).collect(),
+    )))
+}
+
+fn read_file(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    let text = io(call, "read", &path, || fs::read_to_string(&*path))?;
+    Ok(string(text))
+}
+
+/// `readLines(path)` reads the file as a list of lines, without their line
+/// endings.
+fn read_lines(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    let text = io(call, "read", &path, || fs::read_to_string(&*path))?;
+    Ok(Object::List(LoxList::new(
+        text.lines().map(|line| string(line.to_owned())).collect(),
+    )))
+}
+
+/// `removeDir(path)` only removes empty directories.
+fn remove_dir(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    io(call, "remove", &path, || fs::remove_dir(&*path))?;
+    Ok(Object::Nil)
+}
+
+fn remove_file(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    io(call, "remove", &path, || fs::remove_file(&*path))?;
+    Ok(Object::Nil)
+}
+
+/// `writeFile(path, text)` replaces the file's contents, creating it if need
+/// be.
+fn write_file(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let path = path(interpreter, call)?;
+    let text = call.string(1)?;
+    io(call, "write", &path, || fs::write(&*path, text.as_bytes()))?;
+    Ok(Object::Nil)
+}
@@ -0,0 +1,34 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::Arity;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::Object;
+
+use gc::Gc;
+
+/// Natives for reading the interpreter's input, which is stdin unless an
+/// embedder or test provides another source.
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "readAll", Arity::exactly(0), read_all);
+    define_native(globals, "readLine", Arity::exactly(0), read_line);
+}
+
+/// `readAll()` reads the rest of the input, which is empty at the end of it.
+fn read_all(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let text = interpreter
+        .input()
+        .read_all()
+        .map_err(|error| call.error(&format!("Can't read input: {error}.")))?;
+    Ok(Object::String(Gc::new(text)))
+}
+
+/// `readLine()` reads a line without its line ending, or returns nil at the end
+/// of the input.
+fn read_line(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let line = interpreter
+        .input()
+        .read_line()
+        .map_err(|error| call.error(&format!("Can't read input: {error}.")))?;
+    Ok(line.map_or(Object::Nil, |line| Object::String(Gc::new(line))))
+}
@@ -0,0 +1,450 @@
+use crate::environment::Environment;
+use crate::interpreter::{is_truthy, Interpreter};
+use crate::lox_callable::Arity;
+use crate::lox_class::LoxClass;
+use crate::lox_instance::LoxInstance;
+use crate::lox_list::LoxList;
+use crate::lox_map::{LoxMap, MapKey};
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::{with_number_format, Object};
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use gc::Gc;
+
+// Deeper nesting than this is almost certainly a mistake, and recursing on it
+// could overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+// As in JavaScript's `JSON.stringify`. Wider indents don't help readability.
+const MAX_INDENT: usize = 10;
+
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "parseJson", Arity::range(1, 2), parse_json);
+    define_native(globals, "toJson", Arity::range(1, 2), to_json);
+}
+
+/// `parseJson(text, asInstances)` parses JSON objects into maps, or into
+/// instances of a class named `JsonObject` if `asInstances` is true.
+fn parse_json(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let text = call.string(0)?;
+    let as_instances = call.arguments.get(1).is_some_and(is_truthy);
+    let mut parser = JsonParser {
+        chars: text.chars().collect(),
+        current: 0,
+        depth: 0,
+        class: as_instances.then(|| LoxClass::new("JsonObject", None, HashMap::new())),
+    };
+    parser.parse().map_err(|message| {
+        let (line, column) = parser.position();
+        call.error(&format!(
+            "Invalid JSON at line {line}, column {column}: {message}"
+        ))
+    })
+}
+
+type ParseResult<T> = std::result::Result<T, String>;
+
+struct JsonParser {
+    chars: Vec<char>,
+    current: usize,
+    depth: usize,
+    // The class of the instances objects are parsed into, if not maps.
+    class: Option<LoxClass>,
+}
+
+impl JsonParser {
+    fn parse(&mut self) -> ParseResult<Object> {
+        let value = self.value()?;
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) => Err(format!("Unexpected '{c}' after the value.")),
+            None => Ok(value),
+        }
+    }
+
+    fn value(&mut self) -> ParseResult<Object> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Object::String(Gc::new(self.string()?))),
+            Some('t') => self.literal("true", Object::Boolean(true)),
+            Some('f') => self.literal("false", Object::Boolean(false)),
+            Some('n') => self.literal("null", Object::Nil),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("Unexpected '{c}'.")),
+            None => Err("Unexpected end of input.".to_owned()),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> ParseResult<Object>,
+    ) -> ParseResult<Object> {
+        if self.depth == MAX_DEPTH {
+            return Err("Too deeply nested.".to_owned());
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> ParseResult<Object> {
+        self.advance();
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if !self.match_('}') {
+            loop {
+                self.skip_whitespace();
+                if self.peek() != Some('"') {
+                    return Err("Expect a string key.".to_owned());
+                }
+                let key = self.string()?;
+                self.skip_whitespace();
+                if !self.match_(':') {
+                    return Err("Expect ':' after key.".to_owned());
+                }
+                fields.push((key, self.value()?));
+                self.skip_whitespace();
+                if self.match_('}') {
+                    break;
+                }
+                if !self.match_(',') {
+                    return Err("Expect ',' or '}' after value.".to_owned());
+                }
+            }
+        }
+
+        if let Some(class) = &self.class {
+            let instance = LoxInstance::new(class.clone());
+            for (key, value) in fields {
+                instance.set_field(&key, value);
+            }
+            return Ok(Object::Instance(instance));
+        }
+        let map = LoxMap::new();
+        for (key, value) in fields {
+            let key = Object::String(Gc::new(key));
+            let map_key = MapKey::new(&key).expect("Expect strings to be valid keys.");
+            map.insert(map_key, key, value);
+        }
+        Ok(Object::Map(map))
+    }
+
+    fn array(&mut self) -> ParseResult<Object> {
+        self.advance();
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if !self.match_(']') {
+            loop {
+                elements.push(self.value()?);
+                self.skip_whitespace();
+                if self.match_(']') {
+                    break;
+                }
+                if !self.match_(',') {
+                    return Err("Expect ',' or ']' after value.".to_owned());
+                }
+            }
+        }
+        Ok(Object::List(LoxList::new(elements)))
+    }
+
+    fn string(&mut self) -> ParseResult<String> {
+        self.advance();
+        let mut string = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(self.escape()?),
+                Some(c) if c < ' ' => {
+                    return Err("Unescaped control character in string.".to_owned())
+                }
+                Some(c) => string.push(c),
+                None => return Err("Unterminated string.".to_owned()),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> ParseResult<char> {
+        match self.advance() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('/') => Ok('/'),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{c}'),
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some('u') => {
+                let unit = self.hex4()?;
+                // Characters outside the Basic Multilingual Plane are written
+                // as a surrogate pair.
+                if (0xD800..0xDC00).contains(&unit) && self.match_('\\') && self.match_('u')
+                {
+                    let low = self.hex4()?;
+                    if (0xDC00..0xE000).contains(&low) {
+                        let code = 0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00);
+                        return char::from_u32(code).ok_or_else(invalid_escape);
+                    }
+                }
+                char::from_u32(unit).ok_or_else(invalid_escape)
+            }
+            _ => Err(invalid_escape()),
+        }
+    }
+
+    fn hex4(&mut self) -> ParseResult<u32> {
+        let mut unit = 0;
+        for _ in 0..4 {
+            let digit = self.advance().and_then(|c| c.to_digit(16));
+            unit = unit * 16 + digit.ok_or_else(invalid_escape)?;
+        }
+        Ok(unit)
+    }
+
+    fn number(&mut self) -> ParseResult<Object> {
+        let start = self.current;
+        self.match_('-');
+        if !self.match_('0') && !self.digits() {
+            return Err("Expect a digit.".to_owned());
+        }
+        let mut is_float = false;
+        if self.match_('.') {
+            if !self.digits() {
+                return Err("Expect a digit after '.'.".to_owned());
+            }
+            is_float = true;
+        }
+        if self.match_('e') || self.match_('E') {
+            if !self.match_('+') {
+                self.match_('-');
+            }
+            if !self.digits() {
+                return Err("Expect a digit in the exponent.".to_owned());
+            }
+            is_float = true;
+        }
+
+        let text: String = self.chars[start..self.current].iter().collect();
+        if let Ok(integer) = text.parse() {
+            if !is_float {
+                return Ok(Object::Integer(integer));
+            }
+        }
+        let number: f64 = text
+            .parse()
+            .expect("Expect JSON numbers to be valid floats.");
+        if number.is_infinite() {
+            // Report the error at the start of the number.
+            self.current = start;
+            return Err("Number is out of range.".to_owned());
+        }
+        Ok(Object::Number(number))
+    }
+
+    /// Consume digits, returning whether there were any.
+    fn digits(&mut self) -> bool {
+        let start = self.current;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.advance();
+        }
+        self.current > start
+    }
+
+    fn literal(&mut self, word: &str, value: Object) -> ParseResult<Object> {
+        for expected in word.chars() {
+            if !self.match_(expected) {
+                return Err(format!("Expect '{word}'."));
+            }
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        {
+            self.advance();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.current += 1;
+        }
+        c
+    }
+
+    fn match_(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.current += 1;
+            return true;
+        }
+        false
+    }
+
+    /// The 1-based line and column of the current character.
+    fn position(&self) -> (usize, usize) {
+        let before = &self.chars[..self.current.min(self.chars.len())];
+        let line = before.iter().filter(|&&c| c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        (line, column)
+    }
+}
+
+fn invalid_escape() -> String {
+    "Invalid escape sequence.".to_owned()
+}
+
+/// `toJson(value, indent)` serializes `value`, pretty-printing it with
+/// `indent` spaces per level, up to 10, if `indent` is given and isn't 0.
+/// Instances are written as objects of their fields.
+fn to_json(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let indent = match call.arguments.get(1) {
+        Some(_) => call.index(1)?,
+        None => 0,
+    };
+    if indent > MAX_INDENT {
+        return Err(call.error(&format!("JSON indent must be at most {MAX_INDENT}.")));
+    }
+    let mut writer = JsonWriter {
+        call,
+        indent,
+        containing: Vec::new(),
+        json: String::new(),
+    };
+    writer.write(&call.arguments[0], 0)?;
+    Ok(Object::String(Gc::new(writer.json)))
+}
+
+struct JsonWriter<'a> {
+    call: &'a NativeCall<'a>,
+    indent: usize,
+    // The addresses of the containers being written, for detecting cycles.
+    containing: Vec<usize>,
+    json: String,
+}
+
+impl JsonWriter<'_> {
+    fn write(&mut self, value: &Object, depth: usize) -> Result<()> {
+        match value {
+            Object::Nil => self.json.push_str("null"),
+            Object::Boolean(b) => write!(self.json, "{b}")?,
+            Object::Integer(n) => write!(self.json, "{n}")?,
+            // JSON numbers are the same whatever the number format option.
+            Object::Number(n) if n.is_finite() => self
+                .json
+                .push_str(&with_number_format(false, || value.to_string())),
+            Object::String(s) => self.string(s),
+            Object::List(list) => {
+                let elements: Vec<_> =
+                    list.elements().into_iter().map(|e| (None, e)).collect();
+                self.container(list.address(), ('[', ']'), elements, depth)?;
+            }
+            Object::Map(map) => {
+                let entries = map
+                    .entries()
+                    .into_iter()
+                    .map(|(key, value)| match &key {
+                        Object::String(key) => Ok((Some(key.to_string()), value)),
+                        _ => Err(self.call.error("JSON object keys must be strings.")),
+                    })
+                    .collect::<Result<_>>()?;
+                self.container(map.address(), ('{', '}'), entries, depth)?;
+            }
+            Object::Instance(instance) => {
+                let fields = instance
+                    .fields()
+                    .into_iter()
+                    .map(|(name, value)| (Some(name), value))
+                    .collect();
+                self.container(instance.address(), ('{', '}'), fields, depth)?;
+            }
+            _ => {
+                return Err(self
+                    .call
+                    .error(&format!("Can't convert '{value}' to JSON.")))
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the elements of an array, or the fields of an object, which have
+    /// keys.
+    fn container(
+        &mut self,
+        address: usize,
+        (open, close): (char, char),
+        items: Vec<(Option<String>, Object)>,
+        depth: usize,
+    ) -> Result<()> {
+        if depth == MAX_DEPTH {
+            return Err(self.call.error("Too deeply nested to convert to JSON."));
+        }
+        if self.containing.contains(&address) {
+            return Err(self
+                .call
+                .error("Can't convert a value which contains itself to JSON."));
+        }
+        self.containing.push(address);
+
+        self.json.push(open);
+        for (index, (key, value)) in items.iter().enumerate() {
+            if index > 0 {
+                self.json.push(',');
+            }
+            self.newline(depth + 1);
+            if let Some(key) = key {
+                self.string(key);
+                self.json.push(':');
+                if self.indent > 0 {
+                    self.json.push(' ');
+                }
+            }
+            self.write(value, depth + 1)?;
+        }
+        if !items.is_empty() {
+            self.newline(depth);
+        }
+        self.json.push(close);
+
+        self.containing.pop();
+        Ok(())
+    }
+
+    fn newline(&mut self, depth: usize) {
+        if self.indent > 0 {
+            self.json.push('\n');
+            self.json.push_str(&" ".repeat(self.indent * depth));
+        }
+    }
+
+    fn string(&mut self, s: &str) {
+        self.json.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.json.push_str("\\\""),
+                '\\' => self.json.push_str("\\\\"),
+                '\n' => self.json.push_str("\\n"),
+                '\r' => self.json.push_str("\\r"),
+                '\t' => self.json.push_str("\\t"),
+                c if c < ' ' => {
+                    let _ = write!(self.json, "\\u{:04x}", c as u32);
+                }
+                c => self.json.push(c),
+            }
+        }
+        self.json.push('"');
+    }
+}
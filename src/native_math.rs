@@ -0,0 +1,216 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::Arity;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::{compare_numbers, Object};
+
+use std::cmp::Ordering;
+use std::f64::consts;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A seedable pseudo-random number generator (SplitMix64). It's fast and
+/// good enough for simulations and games, but not for cryptography.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A generator seeded from the current time.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards.")
+            .as_nanos();
+        Self::new(nanos as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A float in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        // The top 53 bits fill a float's mantissa exactly.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+pub fn define_globals(globals: &Environment) {
+    globals.define("E", Object::Number(consts::E));
+    globals.define("INFINITY", Object::Number(f64::INFINITY));
+    globals.define("NAN", Object::Number(f64::NAN));
+    globals.define("PI", Object::Number(consts::PI));
+
+    define_native(globals, "abs", Arity::exactly(1), abs);
+    define_native(globals, "acos", Arity::exactly(1), acos);
+    define_native(globals, "asin", Arity::exactly(1), asin);
+    define_native(globals, "atan", Arity::exactly(1), atan);
+    define_native(globals, "atan2", Arity::exactly(2), atan2);
+    define_native(globals, "ceil", Arity::exactly(1), ceil);
+    define_native(globals, "cos", Arity::exactly(1), cos);
+    define_native(globals, "exp", Arity::exactly(1), exp);
+    define_native(globals, "floor", Arity::exactly(1), floor);
+    define_native(globals, "log", Arity::range(1, 2), log);
+    define_native(globals, "max", Arity::at_least(1), max);
+    define_native(globals, "min", Arity::at_least(1), min);
+    define_native(globals, "pow", Arity::exactly(2), pow);
+    define_native(globals, "random", Arity::exactly(0), random);
+    define_native(globals, "randomInt", Arity::exactly(2), random_int);
+    define_native(globals, "round", Arity::exactly(1), round);
+    define_native(globals, "seedRandom", Arity::exactly(1), seed_random);
+    define_native(globals, "sin", Arity::exactly(1), sin);
+    define_native(globals, "sqrt", Arity::exactly(1), sqrt);
+    define_native(globals, "tan", Arity::exactly(1), tan);
+    define_native(globals, "trunc", Arity::exactly(1), trunc);
+}
+
+fn unary(call: &NativeCall, f: fn(f64) -> f64) -> Result<Object> {
+    Ok(Object::Number(f(call.number(0)?)))
+}
+
+fn acos(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    unary(call, f64::acos)
+}
+
+fn asin(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    unary(call, f64::asin)
+}
+
+fn atan(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    unary(call, f64::atan)
+}
+
+/// `atan2(y, x)` is the angle of the point (x, y).
+fn atan2(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Number(call.number(0)?.atan2(call.number(1)?)))
+}
+
+fn cos(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    unary(call, f64::cos)
+}
+
+fn exp(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    unary(call, f64::exp)
+}
+
+/// `log(x)` is the natural logarithm, and `log(x, base)` the logarithm to
+/// `base`.
+fn log(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let x = call.number(0)?;
+    match call.arguments.get(1) {
+        Some(_) => Ok(Object::Number(x.log(call.number(1)?))),
+        None => Ok(Object::Number(x.ln())),
+    }
+}
+
+fn pow(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Number(call.number(0)?.powf(call.number(1)?)))
+}
+
+fn sin(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    unary(call, f64::sin)
+}
+
+fn sqrt(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    unary(call, f64::sqrt)
+}
+
+fn tan(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    unary(call, f64::tan)
+}
+
+/// Round a number to a whole one, which is an integer if it fits in one.
+fn rounding(call: &NativeCall, f: fn(f64) -> f64) -> Result<Object> {
+    if let Object::Integer(n) = call.arguments[0] {
+        return Ok(Object::Integer(n));
+    }
+    let x = f(call.number(0)?);
+    if x >= i64::MIN as f64 && x < i64::MAX as f64 {
+        Ok(Object::Integer(x as i64))
+    } else {
+        Ok(Object::Number(x))
+    }
+}
+
+fn ceil(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    rounding(call, f64::ceil)
+}
+
+fn floor(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    rounding(call, f64::floor)
+}
+
+/// Halfway cases round away from zero.
+fn round(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    rounding(call, f64::round)
+}
+
+fn trunc(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    rounding(call, f64::trunc)
+}
+
+fn abs(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    match call.arguments[0] {
+        Object::Integer(n) => n
+            .checked_abs()
+            .map(Object::Integer)
+            .ok_or_else(|| call.error("Integer overflow.")),
+        _ => unary(call, f64::abs),
+    }
+}
+
+/// The argument which compares as `wanted` against all the others, keeping
+/// its type. NaN wins, as it's neither smaller nor larger than anything.
+fn extreme(call: &NativeCall, wanted: Ordering) -> Result<Object> {
+    let mut best = &call.arguments[0];
+    for (index, argument) in call.arguments.iter().enumerate() {
+        call.number(index)?;
+        match compare_numbers(argument, best) {
+            Some(ordering) if ordering == wanted => best = argument,
+            None if !matches!(best, Object::Number(x) if x.is_nan()) => best = argument,
+            _ => {}
+        }
+    }
+    Ok(best.clone())
+}
+
+fn max(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    extreme(call, Ordering::Greater)
+}
+
+fn min(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    extreme(call, Ordering::Less)
+}
+
+/// `random()` is a float in [0, 1).
+fn random(interpreter: &mut Interpreter, _call: &NativeCall) -> Result<Object> {
+    Ok(Object::Number(interpreter.random().next_f64()))
+}
+
+/// `randomInt(low, high)` is an integer between `low` and `high`, inclusive.
+fn random_int(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let low = call.integer(0)?;
+    let high = call.integer(1)?;
+    if low > high {
+        return Err(call.error("Expect the low bound to be at most the high bound."));
+    }
+    let range = high.abs_diff(low) as u128 + 1;
+    // Reducing a 64-bit number modulo the range is very slightly biased for
+    // huge ranges, which doesn't matter here.
+    let offset = (interpreter.random().next_u64() as u128 % range) as u64;
+    Ok(Object::Integer(low.wrapping_add_unsigned(offset)))
+}
+
+fn seed_random(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    *interpreter.random() = Random::new(call.integer(0)? as u64);
+    Ok(Object::Nil)
+}
@@ -0,0 +1,55 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::Arity;
+use crate::lox_list::LoxList;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::lox_return::Exit;
+use crate::object::Object;
+
+use std::env;
+
+use gc::Gc;
+
+/// Natives for the script's interaction with the process running it.
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "args", Arity::exactly(0), args);
+    define_native(globals, "exit", Arity::range(0, 1), exit);
+    define_native(globals, "getEnv", Arity::exactly(1), get_env);
+}
+
+/// `args()` lists the arguments given after the script's path.
+fn args(interpreter: &mut Interpreter, _call: &NativeCall) -> Result<Object> {
+    Ok(Object::List(LoxList::new(
+        interpreter
+            .options()
+            .args
+            .iter()
+            .map(|arg| Object::String(Gc::new(arg.clone())))
+            .collect(),
+    )))
+}
+
+/// `exit(code)` stops the script, which exits with status `code`, or 0 by
+/// default.
+fn exit(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let code = match call.arguments.first() {
+        Some(_) => call.integer(0)?,
+        None => 0,
+    };
+    match i32::try_from(code) {
+        Ok(code) => Err(Exit {
+            code,
+            paren: call.paren.clone(),
+        }
+        .into()),
+        Err(_) => Err(call.error(&format!("{code} isn't a valid exit code."))),
+    }
+}
+
+/// `getEnv(name)` is the value of an environment variable, or nil if it isn't
+/// set.
+fn get_env(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let name = call.string(0)?;
+    Ok(env::var(&*name).map_or(Object::Nil, |value| Object::String(Gc::new(value))))
+}
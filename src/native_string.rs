@@ -0,0 +1,182 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::Arity;
+use crate::lox_list::LoxList;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::Object;
+
+use gc::Gc;
+
+// A gigabyte, far more than any sensible script needs, so that a mistaken
+// count raises an error rather than exhausting memory.
+const MAX_REPEATED_LENGTH: usize = 1 << 30;
+
+/// String natives, which index strings by character (Unicode scalar value)
+/// rather than by byte.
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "charCode", Arity::range(1, 2), char_code);
+    define_native(globals, "contains", Arity::exactly(2), contains);
+    define_native(globals, "endsWith", Arity::exactly(2), ends_with);
+    define_native(globals, "fromCharCode", Arity::exactly(1), from_char_code);
+    define_native(globals, "indexOf", Arity::exactly(2), index_of);
+    define_native(globals, "join", Arity::exactly(2), join);
+    define_native(globals, "length", Arity::exactly(1), length);
+    define_native(globals, "lower", Arity::exactly(1), lower);
+    define_native(globals, "parseNumber", Arity::exactly(1), parse_number);
+    define_native(globals, "repeat", Arity::exactly(2), repeat);
+    define_native(globals, "replace", Arity::exactly(3), replace);
+    define_native(globals, "split", Arity::exactly(2), split);
+    define_native(globals, "startsWith", Arity::exactly(2), starts_with);
+    define_native(globals, "substring", Arity::range(2, 3), substring);
+    define_native(globals, "trim", Arity::exactly(1), trim);
+    define_native(globals, "upper", Arity::exactly(1), upper);
+}
+
+fn string(text: String) -> Result<Object> {
+    Ok(Object::String(Gc::new(text)))
+}
+
+/// `charCode(s, index)` is the code point of the character at `index`, which
+/// defaults to 0.
+fn char_code(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let s = call.string(0)?;
+    let index = match call.arguments.get(1) {
+        Some(_) => call.index(1)?,
+        None => 0,
+    };
+    match s.chars().nth(index) {
+        Some(c) => Ok(Object::Integer(c as i64)),
+        None => Err(call.error("String index out of range.")),
+    }
+}
+
+fn contains(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Boolean(call.string(0)?.contains(&*call.string(1)?)))
+}
+
+fn ends_with(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Boolean(
+        call.string(0)?.ends_with(&*call.string(1)?),
+    ))
+}
+
+fn from_char_code(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let code = call.integer(0)?;
+    match u32::try_from(code).ok().and_then(char::from_u32) {
+        Some(c) => string(c.to_string()),
+        None => Err(call.error(&format!("{code} isn't a valid character code."))),
+    }
+}
+
+/// `indexOf(s, needle)` is the index of the first occurrence of `needle`, or
+/// -1 if there isn't one.
+fn index_of(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let s = call.string(0)?;
+    let index = match s.find(&*call.string(1)?) {
+        Some(byte_index) => s[..byte_index].chars().count() as i64,
+        None => -1,
+    };
+    Ok(Object::Integer(index))
+}
+
+/// `join(list, separator)` joins the elements of `list`, which needn't be
+/// strings.
+fn join(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let list = call.list(0)?;
+    let separator = call.string(1)?;
+    let elements: Vec<_> = list.elements().iter().map(Object::to_string).collect();
+    string(elements.join(&separator))
+}
+
+fn length(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Integer(call.string(0)?.chars().count() as i64))
+}
+
+fn lower(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    string(call.string(0)?.to_lowercase())
+}
+
+/// `parseNumber(s)` returns nil if `s` isn't a number, so scripts can check
+/// for bad input without an error stopping them.
+fn parse_number(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let s = call.string(0)?;
+    let text = s.trim();
+    let is_numeric = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'));
+    if !is_numeric {
+        return Ok(Object::Nil);
+    }
+    if let Ok(integer) = text.parse() {
+        return Ok(Object::Integer(integer));
+    }
+    Ok(text.parse().map_or(Object::Nil, Object::Number))
+}
+
+fn repeat(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let s = call.string(0)?;
+    let count = call.index(1)?;
+    match s.len().checked_mul(count) {
+        Some(length) if length <= MAX_REPEATED_LENGTH => string(s.repeat(count)),
+        _ => Err(call.error("Repeated string would be too long.")),
+    }
+}
+
+/// `replace(s, from, to)` replaces every occurrence of `from`.
+fn replace(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let from = call.string(1)?;
+    if from.is_empty() {
+        return Err(call.error("Can't replace an empty string."));
+    }
+    string(call.string(0)?.replace(&*from, &call.string(2)?))
+}
+
+/// `split(s, separator)` splits `s` into a list of strings. An empty separator
+/// splits it into characters.
+fn split(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let s = call.string(0)?;
+    let separator = call.string(1)?;
+    let parts: Vec<String> = if separator.is_empty() {
+        s.chars().map(String::from).collect()
+    } else {
+        s.split(&*separator).map(str::to_owned).collect()
+    };
+    Ok(Object::List(LoxList::new(
+        parts
+            .into_iter()
+            .map(|part| Object::String(Gc::new(part)))
+            .collect(),
+    )))
+}
+
+fn starts_with(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Boolean(
+        call.string(0)?.starts_with(&*call.string(1)?),
+    ))
+}
+
+/// `substring(s, start, end)` is the characters from `start` up to, but not
+/// including, `end`, which defaults to the end of the string.
+fn substring(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let s = call.string(0)?;
+    let length = s.chars().count();
+    let start = call.index(1)?;
+    let end = match call.arguments.get(2) {
+        Some(_) => call.index(2)?,
+        None => length,
+    };
+    if start > end || end > length {
+        return Err(call.error("String index out of range."));
+    }
+    string(s.chars().skip(start).take(end - start).collect())
+}
+
+fn trim(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    string(call.string(0)?.trim().to_owned())
+}
+
+fn upper(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    string(call.string(0)?.to_uppercase())
+}
@@ -0,0 +1,559 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::Arity;
+use crate::lox_map::{LoxMap, MapKey};
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::Object;
+
+use std::fmt::Write;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use gc::Gc;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+// Roughly three million years either side of 1970, which keeps the calendar
+// arithmetic well within an i64.
+const MAX_TIMESTAMP: f64 = 1e14;
+
+// More months than there are in the range of timestamps, so that adding months
+// can be checked against it before any calendar arithmetic.
+const MAX_MONTHS: i64 = 3_200_000 * 12;
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Natives for timing and for dates. Dates are timestamps: seconds since
+/// 1970-01-01T00:00:00Z, like `clock()` returns, and all calendar fields are
+/// in UTC.
+pub fn define_globals(globals: &Environment) {
+    start();
+
+    define_native(globals, "addMonths", Arity::exactly(2), add_months);
+    define_native(globals, "dateFields", Arity::exactly(1), date_fields);
+    define_native(globals, "duration", Arity::range(1, 4), duration);
+    define_native(globals, "formatDate", Arity::exactly(2), format_date);
+    define_native(
+        globals,
+        "formatDuration",
+        Arity::exactly(1),
+        format_duration,
+    );
+    define_native(globals, "makeDate", Arity::range(3, 6), make_date);
+    define_native(globals, "monotonic", Arity::exactly(0), monotonic);
+    define_native(globals, "parseDate", Arity::exactly(1), parse_date);
+    define_native(globals, "sleep", Arity::exactly(1), sleep);
+}
+
+/// A moment broken into UTC calendar fields, to the millisecond.
+#[derive(Debug, PartialEq)]
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    millisecond: u32,
+}
+
+impl DateTime {
+    fn from_timestamp(timestamp: f64) -> Option<Self> {
+        if !(-MAX_TIMESTAMP..=MAX_TIMESTAMP).contains(&timestamp) {
+            return None;
+        }
+        let millis = (timestamp * 1000.0).floor() as i64;
+        let (year, month, day) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+        let time = millis.rem_euclid(MILLIS_PER_DAY);
+        Some(Self {
+            year,
+            month,
+            day,
+            hour: (time / 3_600_000) as u32,
+            minute: (time / 60_000 % 60) as u32,
+            second: (time / 1000 % 60) as u32,
+            millisecond: (time % 1000) as u32,
+        })
+    }
+
+    fn days(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    fn timestamp(&self) -> f64 {
+        let seconds = self.days() * 86_400
+            + i64::from(self.hour) * 3600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second);
+        seconds as f64 + f64::from(self.millisecond) / 1000.0
+    }
+
+    /// The ISO weekday, from 1 for Monday to 7 for Sunday.
+    fn weekday(&self) -> u32 {
+        // 1970-01-01 was a Thursday.
+        ((self.days() + 3).rem_euclid(7) + 1) as u32
+    }
+
+    /// The day of the year, from 1.
+    fn year_day(&self) -> u32 {
+        (self.days() - days_from_civil(self.year, 1, 1) + 1) as u32
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days from 1970-01-01 to a date in the proleptic Gregorian
+/// calendar, using Howard Hinnant's algorithm.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn date_time(call: &NativeCall, index: usize) -> Result<DateTime> {
+    DateTime::from_timestamp(call.number(index)?)
+        .ok_or_else(|| call.error("Timestamp out of range."))
+}
+
+fn string(text: String) -> Result<Object> {
+    Ok(Object::String(Gc::new(text)))
+}
+
+/// `addMonths(timestamp, months)` moves a timestamp by calendar months,
+/// clamping the day to the end of shorter months.
+fn add_months(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let mut date = date_time(call, 0)?;
+    let offset = call.integer(1)?;
+    let months = date
+        .year
+        .checked_mul(12)
+        .and_then(|months| months.checked_add(i64::from(date.month) - 1))
+        .and_then(|months| months.checked_add(offset))
+        .filter(|months| (-MAX_MONTHS..=MAX_MONTHS).contains(months))
+        .ok_or_else(|| call.error("Timestamp out of range."))?;
+    date.year = months.div_euclid(12);
+    date.month = months.rem_euclid(12) as u32 + 1;
+    date.day = date.day.min(days_in_month(date.year, date.month));
+    let timestamp = date.timestamp();
+    if timestamp.abs() > MAX_TIMESTAMP {
+        return Err(call.error("Timestamp out of range."));
+    }
+    Ok(Object::Number(timestamp))
+}
+
+/// `dateFields(timestamp)` is a map of the year, month, day, hour, minute,
+/// second, millisecond, weekday (1 for Monday) and yearDay.
+fn date_fields(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let date = date_time(call, 0)?;
+    let map = LoxMap::new();
+    let weekday = date.weekday();
+    let year_day = date.year_day();
+    let fields = [
+        ("year", date.year),
+        ("month", date.month.into()),
+        ("day", date.day.into()),
+        ("hour", date.hour.into()),
+        ("minute", date.minute.into()),
+        ("second", date.second.into()),
+        ("millisecond", date.millisecond.into()),
+        ("weekday", weekday.into()),
+        ("yearDay", year_day.into()),
+    ];
+    for (name, value) in fields {
+        let key = Object::String(Gc::new(name.to_owned()));
+        let map_key = MapKey::new(&key).expect("Expect strings to be valid keys.");
+        map.insert(map_key, key, Object::Integer(value));
+    }
+    Ok(Object::Map(map))
+}
+
+/// `duration(days, hours, minutes, seconds)` is the length of a duration in
+/// seconds, for adding to timestamps.
+fn duration(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let mut seconds = 0.0;
+    for (index, unit) in [86_400.0, 3600.0, 60.0, 1.0]
+        .into_iter()
+        .enumerate()
+        .take(call.arguments.len())
+    {
+        seconds += call.number(index)? * unit;
+    }
+    Ok(Object::Number(seconds))
+}
+
+/// `formatDate(timestamp, pattern)` replaces these specifiers in `pattern`:
+///
+/// - `%Y` the year, `%m` the month, `%d` the day
+/// - `%H` the hour, `%M` the minute, `%S` the second, `%f` the millisecond
+/// - `%j` the day of the year
+/// - `%a` and `%A` the short and full weekday name
+/// - `%b` and `%B` the short and full month name
+/// - `%%` a percent sign
+fn format_date(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let date = date_time(call, 0)?;
+    let pattern = call.string(1)?;
+    let weekday = WEEKDAY_NAMES[date.weekday() as usize - 1];
+    let month = MONTH_NAMES[date.month as usize - 1];
+    let mut text = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => write!(text, "{:04}", date.year)?,
+            Some('m') => write!(text, "{:02}", date.month)?,
+            Some('d') => write!(text, "{:02}", date.day)?,
+            Some('H') => write!(text, "{:02}", date.hour)?,
+            Some('M') => write!(text, "{:02}", date.minute)?,
+            Some('S') => write!(text, "{:02}", date.second)?,
+            Some('f') => write!(text, "{:03}", date.millisecond)?,
+            Some('j') => write!(text, "{:03}", date.year_day())?,
+            Some('a') => text.push_str(&weekday[..3]),
+            Some('A') => text.push_str(weekday),
+            Some('b') => text.push_str(&month[..3]),
+            Some('B') => text.push_str(month),
+            Some('%') => text.push('%'),
+            Some(c) => return Err(call.error(&format!("Unknown date specifier '%{c}'."))),
+            None => return Err(call.error("Expect a specifier after '%'.")),
+        }
+    }
+    string(text)
+}
+
+/// `formatDuration(seconds)` writes a duration in ISO-8601 form, e.g.
+/// `P1DT2H30M` or `PT0.5S`.
+fn format_duration(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let seconds = call.number(0)?;
+    if !seconds.is_finite() {
+        return Err(call.error("Duration must be finite."));
+    }
+    let mut text = String::from(if seconds < 0.0 { "-P" } else { "P" });
+    let millis = (seconds.abs() * 1000.0).round() as u64;
+    let days = millis / MILLIS_PER_DAY as u64;
+    let hours = millis / 3_600_000 % 24;
+    let minutes = millis / 60_000 % 60;
+    let seconds = millis % 60_000;
+    if days > 0 {
+        write!(text, "{days}D")?;
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || days == 0 {
+        text.push('T');
+        if hours > 0 {
+            write!(text, "{hours}H")?;
+        }
+        if minutes > 0 {
+            write!(text, "{minutes}M")?;
+        }
+        if seconds > 0 || (days == 0 && hours == 0 && minutes == 0) {
+            write!(text, "{}", seconds / 1000)?;
+            if !seconds.is_multiple_of(1000) {
+                let fraction = format!("{:03}", seconds % 1000);
+                write!(text, ".{}", fraction.trim_end_matches('0'))?;
+            }
+            text.push('S');
+        }
+    }
+    string(text)
+}
+
+/// `makeDate(year, month, day, hour, minute, second)` is the timestamp of a
+/// UTC date. The time defaults to midnight.
+fn make_date(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let year = call.integer(0)?;
+    let field = |index: usize, low: i64, high: i64, name: &str| -> Result<u32> {
+        let value = match call.arguments.get(index) {
+            Some(_) => call.integer(index)?,
+            None => 0,
+        };
+        if !(low..=high).contains(&value) {
+            return Err(call.error(&format!("{value} isn't a valid {name}.")));
+        }
+        Ok(value as u32)
+    };
+    let month = field(1, 1, 12, "month")?;
+    if year.abs() > 3_000_000 {
+        return Err(call.error(&format!("{year} isn't a valid year.")));
+    }
+    let day = field(2, 1, days_in_month(year, month).into(), "day")?;
+    let hour = field(3, 0, 23, "hour")?;
+    let minute = field(4, 0, 59, "minute")?;
+    let second = match call.arguments.get(5) {
+        Some(_) => call.number(5)?,
+        None => 0.0,
+    };
+    if !(0.0..60.0).contains(&second) {
+        return Err(call.error(&format!("{second} isn't a valid second.")));
+    }
+    let date = DateTime {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second: 0,
+        millisecond: 0,
+    };
+    Ok(Object::Number(date.timestamp() + second))
+}
+
+/// When the program started, or at least when it made its first interpreter.
+fn start() -> Instant {
+    static START: OnceLock<Instant> = OnceLock::new();
+    *START.get_or_init(Instant::now)
+}
+
+/// `monotonic()` is the number of seconds since the program started, from a
+/// clock which never goes backwards, for timing code.
+fn monotonic(_interpreter: &mut Interpreter, _call: &NativeCall) -> Result<Object> {
+    Ok(Object::Number(start().elapsed().as_secs_f64()))
+}
+
+/// `parseDate(text)` is the timestamp of an ISO-8601 date, such as
+/// `2024-02-29`, `2024-02-29T13:45` or `2024-02-29T13:45:30.5+01:00`. Times
+/// without an offset are in UTC.
+fn parse_date(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let text = call.string(0)?;
+    let timestamp = IsoParser {
+        text: text.as_bytes(),
+        current: 0,
+    }
+    .parse()
+    .ok_or_else(|| call.error(&format!("'{text}' isn't a valid ISO-8601 date.")))?;
+    Ok(Object::Number(timestamp))
+}
+
+struct IsoParser<'a> {
+    text: &'a [u8],
+    current: usize,
+}
+
+impl IsoParser<'_> {
+    fn parse(&mut self) -> Option<f64> {
+        let negative = self.match_(b'-');
+        if !negative {
+            self.match_(b'+');
+        }
+        let year = self.digits(4, 7)?;
+        let year = if negative { -year } else { year };
+        self.expect(b'-')?;
+        let month = self.digits(2, 2)?;
+        self.expect(b'-')?;
+        let day = self.digits(2, 2)?;
+        if !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month as u32).into()
+        {
+            return None;
+        }
+        let mut timestamp =
+            days_from_civil(year, month as u32, day as u32) as f64 * 86_400.0;
+
+        if self.match_(b'T') || self.match_(b't') || self.match_(b' ') {
+            let hour = self.digits(2, 2)?;
+            self.expect(b':')?;
+            let minute = self.digits(2, 2)?;
+            let mut second = 0.0;
+            if self.match_(b':') {
+                second = self.digits(2, 2)? as f64;
+                if self.match_(b'.') || self.match_(b',') {
+                    let start = self.current;
+                    self.digits(1, 9)?;
+                    let fraction =
+                        std::str::from_utf8(&self.text[start..self.current]).ok()?;
+                    second += format!("0.{fraction}").parse::<f64>().ok()?;
+                }
+            }
+            if hour > 23 || minute > 59 || second >= 60.0 {
+                return None;
+            }
+            timestamp += (hour * 3600 + minute * 60) as f64 + second;
+
+            if !(self.match_(b'Z') || self.match_(b'z')) {
+                let sign = match self.peek() {
+                    Some(b'+') => Some(-1),
+                    Some(b'-') => Some(1),
+                    _ => None,
+                };
+                if let Some(sign) = sign {
+                    self.current += 1;
+                    let hours = self.digits(2, 2)?;
+                    self.match_(b':');
+                    let minutes = self.digits(2, 2)?;
+                    if hours > 23 || minutes > 59 {
+                        return None;
+                    }
+                    timestamp += (sign * (hours * 3600 + minutes * 60)) as f64;
+                }
+            }
+        }
+
+        (self.current == self.text.len()).then_some(timestamp)
+    }
+
+    /// Parse between `min` and `max` decimal digits.
+    fn digits(&mut self, min: usize, max: usize) -> Option<i64> {
+        let start = self.current;
+        let mut value = 0;
+        while self.current - start < max {
+            match self.peek() {
+                Some(c @ b'0'..=b'9') => value = value * 10 + i64::from(c - b'0'),
+                _ => break,
+            }
+            self.current += 1;
+        }
+        (self.current - start >= min).then_some(value)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.current).copied()
+    }
+
+    fn match_(&mut self, expected: u8) -> bool {
+        if self.peek() == Some(expected) {
+            self.current += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: u8) -> Option<()> {
+        self.match_(expected).then_some(())
+    }
+}
+
+/// `sleep(seconds)` pauses the whole interpreter, including other fibers.
+fn sleep(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let seconds = call.number(0)?;
+    let duration = Duration::try_from_secs_f64(seconds)
+        .map_err(|_| call.error("Can't sleep for a negative or infinite time."))?;
+    thread::sleep(duration);
+    Ok(Object::Nil)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn calendar() {
+        for (days, date) in [
+            (0, (1970, 1, 1)),
+            (-1, (1969, 12, 31)),
+            (11_016, (2000, 2, 29)),
+            (19_782, (2024, 2, 29)),
+            (-719_468, (0, 3, 1)),
+            (2_932_896, (9999, 12, 31)),
+        ] {
+            assert_eq!(civil_from_days(days), date);
+            assert_eq!(days_from_civil(date.0, date.1, date.2), days);
+        }
+        for days in -1_000_000..1_000_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn date_times() {
+        let date = DateTime::from_timestamp(-0.5).unwrap();
+        assert_eq!(
+            date,
+            DateTime {
+                year: 1969,
+                month: 12,
+                day: 31,
+                hour: 23,
+                minute: 59,
+                second: 59,
+                millisecond: 500,
+            }
+        );
+        assert_eq!(date.timestamp(), -0.5);
+        assert_eq!(date.weekday(), 3);
+        assert_eq!(date.year_day(), 365);
+        assert_eq!(DateTime::from_timestamp(f64::NAN), None);
+    }
+
+    #[test]
+    fn iso_dates() {
+        let parse = |text: &str| {
+            IsoParser {
+                text: text.as_bytes(),
+                current: 0,
+            }
+            .parse()
+        };
+        assert_eq!(parse("1970-01-01"), Some(0.0));
+        assert_eq!(parse("1970-01-01T01:00Z"), Some(3600.0));
+        assert_eq!(parse("1970-01-01 01:00:30.25"), Some(3630.25));
+        assert_eq!(parse("1970-01-01T01:00+01:00"), Some(0.0));
+        assert_eq!(parse("1969-12-31T23:00:00-0100"), Some(0.0));
+        assert_eq!(parse("2023-02-29"), None);
+        assert_eq!(parse("2024-13-01"), None);
+        assert_eq!(parse("2024-01-01T24:00"), None);
+        assert_eq!(parse("2024-01-01T"), None);
+        assert_eq!(parse("2024-01-01Z"), None);
+    }
+}
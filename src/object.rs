@@ -1,7 +1,18 @@
+use crate::isolate::LoxIsolate;
 use crate::lox_callable::LoxCallable;
+use crate::lox_channel::LoxChannel;
 use crate::lox_class::LoxClass;
+use crate::lox_enum::{LoxEnum, LoxEnumValue};
+use crate::lox_generator::LoxGenerator;
 use crate::lox_instance::LoxInstance;
+use crate::lox_list::LoxList;
+use crate::lox_map::LoxMap;
+use crate::lox_number;
+use crate::lox_trait::LoxTrait;
+use crate::lox_type::BuiltinType;
 
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
 use std::fmt;
 
 use gc::{Finalize, Gc, Trace};
@@ -10,11 +21,36 @@ use gc::{Finalize, Gc, Trace};
 pub enum Object {
     Boolean(bool),
     Callable(LoxCallable),
+    Channel(LoxChannel),
     Class(LoxClass),
+    Enum(LoxEnum),
+    EnumValue(LoxEnumValue),
+    Generator(LoxGenerator),
     Instance(LoxInstance),
+    Integer(i64),
+    Isolate(LoxIsolate),
+    List(LoxList),
+    Map(LoxMap),
     Nil,
     Number(f64),
     String(Gc<String>),
+    Trait(LoxTrait),
+    Type(#[unsafe_ignore_trace] BuiltinType),
+}
+
+thread_local! {
+    // Whether floats are displayed as the Java reference implementation prints
+    // them. The interpreter running on this thread sets it from its options.
+    static JAVA_NUMBER_FORMAT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` with floats on this thread displayed by `lox_number::java_format`
+/// if `java` is true, wherever they appear, including inside containers.
+pub fn with_number_format<T>(java: bool, f: impl FnOnce() -> T) -> T {
+    let previous = JAVA_NUMBER_FORMAT.replace(java);
+    let result = f();
+    JAVA_NUMBER_FORMAT.set(previous);
+    result
 }
 
 impl fmt::Display for Object {
@@ -22,15 +58,110 @@ impl fmt::Display for Object {
         match self {
             Object::Boolean(x) => write!(f, "{x}"),
             Object::Callable(x) => write!(f, "{x}"),
+            Object::Channel(x) => write!(f, "{x}"),
             Object::Class(x) => write!(f, "{x}"),
+            Object::Enum(x) => write!(f, "{x}"),
+            Object::EnumValue(x) => write!(f, "{x}"),
+            Object::Generator(x) => write!(f, "{x}"),
             Object::Instance(x) => write!(f, "{x}"),
+            Object::Integer(x) => write!(f, "{x}"),
+            Object::Isolate(x) => write!(f, "{x}"),
+            Object::List(x) => write!(f, "{x}"),
+            Object::Map(x) => write!(f, "{x}"),
             Object::Nil => write!(f, "nil"),
+            Object::Number(x) if JAVA_NUMBER_FORMAT.get() => {
+                write!(f, "{}", lox_number::java_format(*x))
+            }
+            // Whole floats keep their decimal point, to tell them apart from integers.
+            Object::Number(x) if x.is_finite() && x.fract() == 0.0 => write!(f, "{x:.1}"),
             Object::Number(x) => write!(f, "{x}"),
             Object::String(x) => write!(f, "{x}"),
+            Object::Trait(x) => write!(f, "{x}"),
+            Object::Type(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+impl Object {
+    pub fn is_number(&self) -> bool {
+        matches!(self, Object::Integer(_) | Object::Number(_))
+    }
+
+    /// The name of the value's type, as returned by `typeOf`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Boolean(_) => "boolean",
+            Object::Callable(LoxCallable::Class(_)) | Object::Class(_) => "class",
+            Object::Callable(_) => "function",
+            Object::Channel(_) => "channel",
+            Object::Enum(_) => "enum",
+            Object::EnumValue(_) => "variant",
+            Object::Generator(_) => "generator",
+            Object::Instance(_) => "instance",
+            Object::Integer(_) => "integer",
+            Object::Isolate(_) => "isolate",
+            Object::List(_) => "list",
+            Object::Map(_) => "map",
+            Object::Nil => "nil",
+            Object::Number(_) => "number",
+            Object::String(_) => "string",
+            Object::Trait(_) => "trait",
+            Object::Type(_) => "type",
+        }
+    }
+}
+
+/// Compare two numbers, returning `None` if either is NaN or isn't a number.
+/// An integer and a float are compared exactly, which converting the integer
+/// to a float would get wrong for integers above 2^53.
+pub fn compare_numbers(a: &Object, b: &Object) -> Option<Ordering> {
+    match (a, b) {
+        (Object::Integer(a), Object::Integer(b)) => Some(a.cmp(b)),
+        (Object::Number(a), Object::Number(b)) => a.partial_cmp(b),
+        (Object::Integer(a), Object::Number(b)) => compare_integer_to_float(*a, *b),
+        (Object::Number(a), Object::Integer(b)) => {
+            compare_integer_to_float(*b, *a).map(Ordering::reverse)
         }
+        _ => None,
+    }
+}
+
+fn compare_integer_to_float(integer: i64, float: f64) -> Option<Ordering> {
+    match (integer as f64).partial_cmp(&float)? {
+        // Rounding the integer gave the float, so the float is a whole number
+        // within a rounding error of the integer, which 2^63 is out of range of.
+        Ordering::Equal if float >= i64::MAX as f64 => Some(Ordering::Less),
+        Ordering::Equal => Some(integer.cmp(&(float as i64))),
+        ordering => Some(ordering),
     }
 }
 
+/// Display a container which may contain itself, directly or indirectly. If
+/// the container at `address` is already being displayed further up the stack,
+/// write `placeholder` instead of recursing forever.
+pub fn display_container<F>(
+    f: &mut fmt::Formatter,
+    address: usize,
+    placeholder: &str,
+    display: F,
+) -> fmt::Result
+where
+    F: FnOnce(&mut fmt::Formatter) -> fmt::Result,
+{
+    thread_local! {
+        static DISPLAYING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    }
+
+    if DISPLAYING.with(|d| d.borrow().contains(&address)) {
+        return write!(f, "{placeholder}");
+    }
+
+    DISPLAYING.with(|d| d.borrow_mut().push(address));
+    let result = display(f);
+    DISPLAYING.with(|d| d.borrow_mut().pop());
+    result
+}
+
 // Doing this instead of deriving PartialEq for Object due to
 // https://github.com/rust-lang/rust/issues/78808.
 impl PartialEq for Object {
@@ -38,11 +169,25 @@ impl PartialEq for Object {
         match (self, other) {
             (Object::Boolean(a), Object::Boolean(b)) => a == b,
             (Object::Callable(a), Object::Callable(b)) => a == b,
+            (Object::Channel(a), Object::Channel(b)) => a == b,
             (Object::Class(a), Object::Class(b)) => a == b,
+            (Object::Enum(a), Object::Enum(b)) => a == b,
+            (Object::EnumValue(a), Object::EnumValue(b)) => a == b,
+            (Object::Generator(a), Object::Generator(b)) => a == b,
             (Object::Instance(a), Object::Instance(b)) => a == b,
+            (Object::Integer(a), Object::Integer(b)) => a == b,
+            (Object::Integer(_), Object::Number(_))
+            | (Object::Number(_), Object::Integer(_)) => {
+                compare_numbers(self, other) == Some(Ordering::Equal)
+            }
+            (Object::Isolate(a), Object::Isolate(b)) => a == b,
+            (Object::List(a), Object::List(b)) => a == b,
+            (Object::Map(a), Object::Map(b)) => a == b,
             (Object::Nil, Object::Nil) => true,
             (Object::Number(a), Object::Number(b)) => a == b,
             (Object::String(a), Object::String(b)) => a == b,
+            (Object::Trait(a), Object::Trait(b)) => a == b,
+            (Object::Type(a), Object::Type(b)) => a == b,
             _ => false,
         }
     }
@@ -6,6 +6,7 @@ use crate::token::Token;
 use crate::token_type::TokenType::{self, self as TT};
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{self, Debug, Display};
 
@@ -27,6 +28,8 @@ where
     F: FnMut(Gc<Token>, &str),
 {
     tokens: Vec<Gc<Token>>,
+    // The doc comments before each token, by the token's index.
+    docs: HashMap<usize, String>,
     current: RefCell<usize>,
     error_handler: RefCell<F>,
 }
@@ -36,8 +39,26 @@ where
     F: FnMut(Gc<Token>, &str),
 {
     pub fn new(tokens: Vec<Gc<Token>>, error_handler: F) -> Self {
+        // Doc comments are taken out of the token stream, so the grammar
+        // doesn't have to allow for them everywhere.
+        let mut docs = HashMap::new();
+        let mut lines = Vec::new();
+        let mut code = Vec::new();
+        for token in tokens {
+            if token.type_ == TT::DocComment {
+                lines.push(token.literal.to_string());
+                continue;
+            }
+            if !lines.is_empty() {
+                docs.insert(code.len(), lines.join("\n"));
+                lines.clear();
+            }
+            code.push(token);
+        }
+
         Self {
-            tokens,
+            tokens: code,
+            docs,
             current: 0.into(),
             error_handler: error_handler.into(),
         }
@@ -58,31 +79,41 @@ where
     }
 
     fn declaration(&self) -> Option<Result<Stmt>> {
+        let doc = self.doc_comment();
         let stmt_result = if self.match_(&[TT::Var]) {
             self.var_declaration()
+        } else if self.match_(&[TT::Const]) {
+            self.const_declaration()
         } else if self.match_(&[TT::Class]) {
-            self.class_declaration()
+            self.class_declaration(doc)
+        } else if self.match_(&[TT::Trait]) {
+            self.trait_declaration()
+        } else if self.match_(&[TT::Enum]) {
+            self.enum_declaration()
         } else if self.match_(&[TT::Fun]) {
-            self.function("function")
+            self.function("function", doc)
                 .map(|f| Stmt::Function(Gc::new(f)))
         } else {
             self.statement()
         };
         match stmt_result {
-            Err(error) => {
-                return match error.downcast_ref::<ParseError>() {
-                    Some(_) => {
-                        self.synchronize();
-                        None
-                    }
-                    None => Some(Err(error)),
+            Err(error) => match error.downcast_ref::<ParseError>() {
+                Some(_) => {
+                    self.synchronize();
+                    None
                 }
-            }
+                None => Some(Err(error)),
+            },
             Ok(res) => Some(Ok(res)),
         }
     }
 
-    fn class_declaration(&self) -> Result<Stmt> {
+    /// The doc comment before the current token, if any.
+    fn doc_comment(&self) -> Option<String> {
+        self.docs.get(&*self.current.borrow()).cloned()
+    }
+
+    fn class_declaration(&self, doc: Option<String>) -> Result<Stmt> {
         let name = self.consume(TT::Identifier, "Expect class name.")?;
 
         let superclass = if self.match_(&[TT::Less]) {
@@ -92,16 +123,61 @@ where
             None
         };
 
+        let mut traits = Vec::new();
+        if self.match_(&[TT::With]) {
+            loop {
+                self.consume(TT::Identifier, "Expect trait name.")?;
+                traits.push(expr::Variable::new(self.previous()).into());
+                if !self.match_(&[TT::Comma]) {
+                    break;
+                }
+            }
+        }
+
         self.consume(TT::LeftBrace, "Expect '{' before class body.")?;
 
         let mut methods = Vec::new();
         while !self.check(TT::RightBrace) && !self.is_at_end() {
-            methods.push(Gc::new(self.function("method")?));
+            let doc = self.doc_comment();
+            methods.push(Gc::new(self.function("method", doc)?));
         }
 
         self.consume(TT::RightBrace, "Expect '}' after class body.")?;
 
-        Ok(stmt::Class::make(name, superclass, methods))
+        Ok(stmt::Class::make(name, superclass, traits, methods, doc))
+    }
+
+    fn trait_declaration(&self) -> Result<Stmt> {
+        let name = self.consume(TT::Identifier, "Expect trait name.")?;
+        self.consume(TT::LeftBrace, "Expect '{' before trait body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check(TT::RightBrace) && !self.is_at_end() {
+            let doc = self.doc_comment();
+            methods.push(Gc::new(self.function("method", doc)?));
+        }
+
+        self.consume(TT::RightBrace, "Expect '}' after trait body.")?;
+
+        Ok(stmt::Trait::make(name, methods))
+    }
+
+    fn enum_declaration(&self) -> Result<Stmt> {
+        let name = self.consume(TT::Identifier, "Expect enum name.")?;
+        self.consume(TT::LeftBrace, "Expect '{' before enum body.")?;
+
+        let mut variants = Vec::new();
+        while !self.check(TT::RightBrace) && !self.is_at_end() {
+            variants.push(self.consume(TT::Identifier, "Expect variant name.")?);
+            // A trailing comma after the last variant is allowed.
+            if !self.match_(&[TT::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(TT::RightBrace, "Expect '}' after enum body.")?;
+
+        Ok(stmt::Enum::make(name, variants))
     }
 
     fn statement(&self) -> Result<Stmt> {
@@ -111,6 +187,9 @@ where
         if self.match_(&[TT::If]) {
             return self.if_statement();
         }
+        if self.match_(&[TT::Match]) {
+            return self.match_statement();
+        }
         if self.match_(&[TT::Print]) {
             return self.print_statement();
         }
@@ -120,6 +199,9 @@ where
         if self.match_(&[TT::While]) {
             return self.while_statement();
         }
+        if self.match_(&[TT::Yield]) {
+            return self.yield_statement();
+        }
         if self.match_(&[TT::LeftBrace]) {
             return Ok(stmt::Block::make(self.block()?));
         }
@@ -127,11 +209,15 @@ where
     }
 
     fn for_statement(&self) -> Result<Stmt> {
+        let keyword = self.previous();
         self.consume(TT::LeftParen, "Expect '(' after 'for'.")?;
 
         let initializer = if self.match_(&[TT::Semicolon]) {
             None
         } else if self.match_(&[TT::Var]) {
+            if self.check_next(TT::In) {
+                return self.for_in_statement(keyword);
+            }
             Some(self.var_declaration()?)
         } else {
             Some(self.expression_statement()?)
@@ -170,6 +256,16 @@ where
         Ok(body)
     }
 
+    fn for_in_statement(&self, keyword: Gc<Token>) -> Result<Stmt> {
+        let name = self.consume(TT::Identifier, "Expect variable name.")?;
+        self.consume(TT::In, "Expect 'in' after variable name.")?;
+        let iterable = self.expression()?;
+        self.consume(TT::RightParen, "Expect ')' after for clauses.")?;
+        let body = self.statement()?;
+
+        Ok(stmt::ForIn::make(keyword, name, iterable, body))
+    }
+
     fn if_statement(&self) -> Result<Stmt> {
         self.consume(TT::LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
@@ -185,10 +281,110 @@ where
         Ok(stmt::If::make(condition, then_branch, else_branch))
     }
 
+    fn match_statement(&self) -> Result<Stmt> {
+        let keyword = self.previous();
+        self.consume(TT::LeftParen, "Expect '(' after 'match'.")?;
+        let value = self.expression()?;
+        self.consume(TT::RightParen, "Expect ')' after match value.")?;
+        self.consume(TT::LeftBrace, "Expect '{' before match arms.")?;
+
+        let mut arms = Vec::new();
+        while !self.check(TT::RightBrace) && !self.is_at_end() {
+            let pattern = self.pattern()?;
+            let guard = if self.match_(&[TT::If]) {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.consume(TT::FatArrow, "Expect '=>' after pattern.")?;
+            let body = self.statement()?;
+            arms.push(stmt::MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+            // Arms may optionally be separated by commas.
+            self.match_(&[TT::Comma]);
+        }
+
+        self.consume(TT::RightBrace, "Expect '}' after match arms.")?;
+        Ok(stmt::Match::make(keyword, value, arms))
+    }
+
+    fn pattern(&self) -> Result<stmt::Pattern> {
+        if self.match_(&[TT::False]) {
+            return Ok(stmt::Pattern::Literal(Object::Boolean(false)));
+        }
+        if self.match_(&[TT::True]) {
+            return Ok(stmt::Pattern::Literal(Object::Boolean(true)));
+        }
+        if self.match_(&[TT::Nil]) {
+            return Ok(stmt::Pattern::Literal(Object::Nil));
+        }
+        if self.match_(&[TT::Number, TT::String]) {
+            return Ok(stmt::Pattern::Literal(self.previous().literal.clone()));
+        }
+        if self.match_(&[TT::Minus]) {
+            let number = self.consume(TT::Number, "Expect number after '-' in pattern.")?;
+            match number.literal {
+                Object::Integer(n) => {
+                    return Ok(stmt::Pattern::Literal(Object::Integer(-n)))
+                }
+                Object::Number(n) => return Ok(stmt::Pattern::Literal(Object::Number(-n))),
+                _ => (),
+            }
+        }
+
+        if self.match_(&[TT::Identifier]) {
+            let name = self.previous();
+            if name.lexeme == "_" {
+                return Ok(stmt::Pattern::Wildcard);
+            }
+            if self.check(TT::Dot) {
+                let mut value = expr::Variable::make(name);
+                while self.match_(&[TT::Dot]) {
+                    let property =
+                        self.consume(TT::Identifier, "Expect property name after '.'.")?;
+                    value = expr::Get::make(value, property);
+                }
+                return Ok(stmt::Pattern::Value(value));
+            }
+            if !self.match_(&[TT::LeftParen]) {
+                return Ok(stmt::Pattern::Binding(name));
+            }
+
+            let mut fields = Vec::new();
+            if !self.check(TT::RightParen) {
+                loop {
+                    let field = self.consume(TT::Identifier, "Expect field name.")?;
+                    let pattern = if self.match_(&[TT::Colon]) {
+                        self.pattern()?
+                    } else {
+                        stmt::Pattern::Binding(field.clone())
+                    };
+                    fields.push((field, pattern));
+                    if !self.match_(&[TT::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(TT::RightParen, "Expect ')' after field patterns.")?;
+
+            return Ok(stmt::Pattern::Class(
+                expr::Variable::new(name).into(),
+                fields,
+            ));
+        }
+
+        let token = self.peek();
+        Err(self.error(&token, "Expect pattern.").into())
+    }
+
     fn print_statement(&self) -> Result<Stmt> {
+        let keyword = self.previous();
         let value = self.expression()?;
         self.consume(TT::Semicolon, "Expect ';' after value.")?;
-        Ok(stmt::Print::make(value))
+        Ok(stmt::Print::make(keyword, value))
     }
 
     fn return_statement(&self) -> Result<Stmt> {
@@ -204,6 +400,19 @@ where
         Ok(stmt::Return::make(keyword, value))
     }
 
+    fn yield_statement(&self) -> Result<Stmt> {
+        let keyword = self.previous();
+        let value = if self.check(TT::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        self.consume(TT::Semicolon, "Expect ';' after yield value.")?;
+
+        Ok(stmt::Yield::make(keyword, value))
+    }
+
     fn var_declaration(&self) -> Result<Stmt> {
         let name = self.consume(TT::Identifier, "Expect variable name.")?;
 
@@ -214,7 +423,15 @@ where
         };
 
         self.consume(TT::Semicolon, "Expect ';' after variable declaration.")?;
-        Ok(stmt::Var::make(name, initializer))
+        Ok(stmt::Var::make(name, initializer, false))
+    }
+
+    fn const_declaration(&self) -> Result<Stmt> {
+        let name = self.consume(TT::Identifier, "Expect constant name.")?;
+        self.consume(TT::Equal, "Expect '=' after constant name.")?;
+        let initializer = self.expression()?;
+        self.consume(TT::Semicolon, "Expect ';' after constant declaration.")?;
+        Ok(stmt::Var::make(name, Some(initializer), true))
     }
 
     fn while_statement(&self) -> Result<Stmt> {
@@ -232,17 +449,39 @@ where
         Ok(stmt::Expression::make(expr))
     }
 
-    fn function(&self, kind: &str) -> Result<stmt::Function> {
+    fn function(&self, kind: &str, doc: Option<String>) -> Result<stmt::Function> {
         let name = self.consume(TT::Identifier, &format!("Expect {kind} name."))?;
         self.consume(TT::LeftParen, &format!("Expect '(' after {kind} name."))?;
-        let mut parameters = Vec::new();
+        let mut parameters: Vec<stmt::Parameter> = Vec::new();
+        let mut rest = None;
         if !self.check(TT::RightParen) {
             loop {
                 if parameters.len() >= 255 {
                     self.error(&self.peek(), "Can't have more than 255 parameters.");
                 }
 
-                parameters.push(self.consume(TT::Identifier, "Expect parameter name.")?);
+                if self.match_(&[TT::DotDotDot]) {
+                    rest =
+                        Some(self.consume(TT::Identifier, "Expect rest parameter name.")?);
+                    if self.check(TT::Comma) {
+                        self.error(&self.peek(), "Rest parameter must be last.");
+                    }
+                    break;
+                }
+
+                let name = self.consume(TT::Identifier, "Expect parameter name.")?;
+                let default = if self.match_(&[TT::Equal]) {
+                    Some(self.expression()?)
+                } else {
+                    if parameters.last().is_some_and(|p| p.default.is_some()) {
+                        self.error(
+                            &name,
+                            "Can't have a parameter without a default after one with a default.",
+                        );
+                    }
+                    None
+                };
+                parameters.push(stmt::Parameter { name, default });
 
                 if !self.match_(&[TT::Comma]) {
                     break;
@@ -253,7 +492,15 @@ where
 
         self.consume(TT::LeftBrace, &format!("Expect '{{' before {kind} body."))?;
         let body = self.block()?;
-        Ok(stmt::Function::new(name, parameters, body))
+        let is_generator = body.iter().any(Stmt::contains_yield);
+        Ok(stmt::Function::new(
+            name,
+            parameters,
+            rest,
+            body,
+            is_generator,
+            doc,
+        ))
     }
 
     fn block(&self) -> Result<Vec<Stmt>> {
@@ -328,7 +575,13 @@ where
     fn comparison(&self) -> Result<Expr> {
         let mut expr = self.term()?;
 
-        while self.match_(&[TT::Greater, TT::GreaterEqual, TT::Less, TT::LessEqual]) {
+        while self.match_(&[
+            TT::Greater,
+            TT::GreaterEqual,
+            TT::Is,
+            TT::Less,
+            TT::LessEqual,
+        ]) {
             let operator = self.previous();
             let right = self.term()?;
             expr = expr::Binary::make(expr, operator, right);
@@ -373,14 +626,28 @@ where
 
     fn finish_call(&self, callee: Expr) -> Result<Expr> {
         let mut arguments = Vec::new();
+        let mut named_arguments = Vec::new();
 
         if !self.check(TT::RightParen) {
             loop {
-                if arguments.len() >= 255 {
+                if arguments.len() + named_arguments.len() >= 255 {
                     self.error(&self.peek(), "Can't have more than 255 arguments.");
                 }
 
-                arguments.push(self.expression()?);
+                if self.check(TT::Identifier) && self.check_next(TT::Colon) {
+                    let name = self.advance();
+                    self.advance();
+                    named_arguments.push((name, self.expression()?));
+                } else {
+                    if !named_arguments.is_empty() {
+                        self.error(
+                            &self.peek(),
+                            "Can't have a positional argument after a named argument.",
+                        );
+                    }
+                    arguments.push(self.expression()?);
+                }
+
                 if !self.match_(&[TT::Comma]) {
                     break;
                 }
@@ -389,7 +656,7 @@ where
 
         let paren = self.consume(TT::RightParen, "Expect ')' after arguments.")?;
 
-        Ok(expr::Call::make(callee, paren, arguments))
+        Ok(expr::Call::make(callee, paren, arguments, named_arguments))
     }
 
     fn call(&self) -> Result<Expr> {
@@ -476,6 +743,13 @@ where
         self.peek().type_ == type_
     }
 
+    fn check_next(&self, type_: TokenType) -> bool {
+        match self.tokens.get(*self.current.borrow() + 1) {
+            Some(token) => token.type_ == type_,
+            None => false,
+        }
+    }
+
     fn advance(&self) -> Gc<Token> {
         if !self.is_at_end() {
             *self.current.borrow_mut() += 1;
@@ -510,13 +784,18 @@ where
 
             match self.peek().type_ {
                 TT::Class
+                | TT::Const
+                | TT::Enum
                 | TT::Fun
                 | TT::Var
                 | TT::For
                 | TT::If
+                | TT::Match
                 | TT::While
                 | TT::Print
-                | TT::Return => {
+                | TT::Return
+                | TT::Trait
+                | TT::Yield => {
                     return;
                 }
                 _ => self.advance(),
@@ -529,6 +808,7 @@ where
 mod test {
     use super::*;
     use crate::pretty_printer::AstPrinter;
+    use crate::scanner::Scanner;
 
     use std::cell::RefCell;
 
@@ -538,15 +818,15 @@ mod test {
 
         let tokens = vec![
             Token::new(TT::LeftParen, "(", Object::Nil, 1).into(),
-            Token::new(TT::Number, "1", Object::Number(1.0), 1).into(),
+            Token::new(TT::Number, "1", Object::Integer(1), 1).into(),
             Token::new(TT::Plus, "+", Object::Nil, 1).into(),
-            Token::new(TT::Number, "2", Object::Number(2.0), 1).into(),
+            Token::new(TT::Number, "2", Object::Integer(2), 1).into(),
             Token::new(TT::Minus, "-", Object::Nil, 1).into(),
             Token::new(TT::Number, "0.5", Object::Number(0.5), 1).into(),
             Token::new(TT::RightParen, ")", Object::Nil, 1).into(),
             Token::new(TT::Star, "*", Object::Nil, 1).into(),
             Token::new(TT::Minus, "-", Object::Nil, 1).into(),
-            Token::new(TT::Number, "4", Object::Number(4.0), 1).into(),
+            Token::new(TT::Number, "4", Object::Integer(4), 1).into(),
             Token::new(TT::Semicolon, ";", Object::Nil, 1).into(),
             Token::new(TT::Eof, "", Object::Nil, 1).into(),
         ];
@@ -568,4 +848,66 @@ mod test {
             panic!("Expected an expression statement");
         }
     }
+
+    #[test]
+    fn parameter_errors() {
+        let errors = RefCell::new(Vec::new());
+        let source = r"
+            fun f(a = 1, b) {}
+            f(a: 1, 2);
+            fun g(...rest, a) {}
+        ";
+        let tokens =
+            Scanner::new(source, |_, _| panic!("Unexpected scan error.")).scan_tokens();
+
+        Parser::new(tokens, |_, m| {
+            errors.borrow_mut().push(m.to_owned());
+        })
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            *errors.borrow(),
+            vec![
+                "Can't have a parameter without a default after one with a default.",
+                "Can't have a positional argument after a named argument.",
+                "Rest parameter must be last.",
+                "Expect ')' after parameters.",
+            ]
+        );
+    }
+
+    #[test]
+    fn doc_comments() {
+        let source = r"
+            /// A point.
+            /// In two dimensions.
+            class Point {
+                /// Make a point.
+                init(x, y) {}
+                // Not a doc comment.
+                norm() {}
+            }
+            /// Ignored.
+            var a;
+            /// Add.
+            fun add(a, b) {}
+        ";
+        let tokens =
+            Scanner::new(source, |_, _| panic!("Unexpected scan error.")).scan_tokens();
+        let statements = Parser::new(tokens, |_, m| panic!("Unexpected parse error: {m}"))
+            .parse()
+            .unwrap();
+
+        let Stmt::Class(class) = &statements[0] else {
+            panic!("Expected a class declaration");
+        };
+        assert_eq!(class.doc.as_deref(), Some("A point.\nIn two dimensions."));
+        assert_eq!(class.methods[0].doc.as_deref(), Some("Make a point."));
+        assert_eq!(class.methods[1].doc, None);
+        let Stmt::Function(function) = &statements[2] else {
+            panic!("Expected a function declaration");
+        };
+        assert_eq!(function.doc.as_deref(), Some("Add."));
+    }
 }
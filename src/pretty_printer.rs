@@ -59,8 +59,8 @@ mod test {
     fn print_exprs() {
         let minus = Token::new(TokenType::Minus, "-", Object::Nil, 1).into();
         let star = Token::new(TokenType::Star, "*", Object::Nil, 1).into();
-        let num123 = Object::Number(123.0).into();
-        let num4567 = Object::Number(45.67).into();
+        let num123 = Object::Integer(123);
+        let num4567 = Object::Number(45.67);
         let expr = Binary::make(
             Unary::make(minus, Literal::make(num123)),
             star,
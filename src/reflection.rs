@@ -0,0 +1,118 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_list::LoxList;
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::Object;
+
+use gc::Gc;
+
+/// Natives for inspecting values at runtime.
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "arityOf", Arity::exactly(1), arity_of);
+    define_native(globals, "classOf", Arity::exactly(1), class_of);
+    define_native(globals, "deleteField", Arity::exactly(2), delete_field);
+    define_native(globals, "fields", Arity::exactly(1), fields);
+    define_native(globals, "getField", Arity::exactly(2), get_field);
+    define_native(globals, "hasField", Arity::exactly(2), has_field);
+    define_native(globals, "methods", Arity::exactly(1), methods);
+    define_native(globals, "nameOf", Arity::exactly(1), name_of);
+    define_native(globals, "setField", Arity::exactly(3), set_field);
+    define_native(globals, "superclassOf", Arity::exactly(1), superclass_of);
+    define_native(globals, "typeOf", Arity::exactly(1), type_of);
+}
+
+fn string(text: &str) -> Object {
+    Object::String(Gc::new(text.to_owned()))
+}
+
+fn strings(texts: Vec<String>) -> Object {
+    Object::List(LoxList::new(
+        texts.iter().map(|text| string(text)).collect(),
+    ))
+}
+
+fn callable(call: &NativeCall) -> Result<LoxCallable> {
+    match &call.arguments[0] {
+        Object::Callable(callable) => Ok(callable.clone()),
+        Object::Class(class) => Ok(LoxCallable::Class(class.clone())),
+        _ => Err(call.error(&format!(
+            "Argument 1 to '{}' must be a function or class.",
+            call.name
+        ))),
+    }
+}
+
+/// `arityOf(fn)` is the number of arguments `fn` requires.
+fn arity_of(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Integer(callable(call)?.arity().min as i64))
+}
+
+fn class_of(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(Object::Class(call.instance(0)?.class().clone()))
+}
+
+/// `deleteField(instance, name)` returns whether the instance had the field.
+fn delete_field(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let instance = call.instance(0)?;
+    let name = call.string(1)?;
+    Ok(Object::Boolean(instance.remove_field(&name)))
+}
+
+/// `fields(instance)` lists the names of the instance's fields, sorted.
+fn fields(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let instance = call.instance(0)?;
+    Ok(strings(
+        instance
+            .fields()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect(),
+    ))
+}
+
+fn get_field(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let instance = call.instance(0)?;
+    let name = call.string(1)?;
+    instance
+        .field(&name)
+        .ok_or_else(|| call.error(&format!("Undefined field '{name}'.")))
+}
+
+fn has_field(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let instance = call.instance(0)?;
+    let name = call.string(1)?;
+    Ok(Object::Boolean(instance.field(&name).is_some()))
+}
+
+/// `methods(class)` lists the names of the class's methods, including
+/// inherited ones, sorted.
+fn methods(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(strings(call.class(0)?.method_names()))
+}
+
+fn name_of(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(string(callable(call)?.name()))
+}
+
+/// `setField(instance, name, value)` returns `value`.
+fn set_field(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let instance = call.instance(0)?;
+    let name = call.string(1)?;
+    let value = call.arguments[2].clone();
+    instance.set_field(&name, value.clone());
+    Ok(value)
+}
+
+/// `superclassOf(class)` is nil for classes without a superclass.
+fn superclass_of(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(call
+        .class(0)?
+        .superclass()
+        .map_or(Object::Nil, Object::Class))
+}
+
+fn type_of(_interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    Ok(string(call.arguments[0].type_name()))
+}
@@ -1,11 +1,11 @@
 use crate::expr::{self, Expr};
 use crate::interpreter::Interpreter;
 use crate::lox_result::Result;
-use crate::stmt::{self, Stmt};
+use crate::stmt::{self, Pattern, Stmt};
 use crate::token::Token;
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone, Copy, PartialEq)]
 enum FunctionType {
@@ -20,6 +20,7 @@ enum ClassType {
     None,
     Class,
     SubClass,
+    Trait,
 }
 
 pub struct Resolver<'a, F>
@@ -28,8 +29,16 @@ where
 {
     interpreter: &'a mut Interpreter,
     scopes: Vec<HashMap<&'a str, bool>>,
+    // The names declared with `const` in each scope in `scopes`.
+    constants: Vec<HashSet<&'a str>>,
     current_function: FunctionType,
+    // Whether the function being resolved is a generator.
+    in_generator: bool,
     current_class: ClassType,
+    // The method names of the traits declared so far, for detecting conflicts
+    // between the traits a class mixes in. The first map is for globals, and
+    // the rest are for each scope in `scopes`.
+    traits: Vec<HashMap<&'a str, Vec<&'a str>>>,
     error_handler: RefCell<F>,
 }
 
@@ -41,8 +50,11 @@ where
         Self {
             interpreter,
             scopes: Vec::new(),
+            constants: Vec::new(),
             current_function: FunctionType::None,
+            in_generator: false,
             current_class: ClassType::None,
+            traits: vec![HashMap::new()],
             error_handler: error_handler.into(),
         }
     }
@@ -74,6 +86,11 @@ where
             self.visit_variable_expr(superclass)?;
         }
 
+        for trait_ in &stmt.traits {
+            self.visit_variable_expr(trait_)?;
+        }
+        self.check_trait_conflicts(stmt);
+
         if stmt.superclass.is_some() {
             self.begin_scope();
             self.scopes.last_mut().unwrap().insert("super", true);
@@ -102,11 +119,85 @@ where
         Ok(())
     }
 
+    /// Report methods provided by more than one of a class's traits which the
+    /// class doesn't override. Only traits declared in the code being resolved
+    /// are known here, so the interpreter checks again at runtime.
+    fn check_trait_conflicts(&self, stmt: &stmt::Class) {
+        let mut providers: HashMap<&str, &str> = HashMap::new();
+        let mut seen = HashSet::new();
+
+        for trait_ in &stmt.traits {
+            let trait_name = trait_.name.lexeme.as_str();
+            if !seen.insert(trait_name) {
+                self.error(
+                    &trait_.name,
+                    &format!("Trait '{trait_name}' is mixed in more than once."),
+                );
+                continue;
+            }
+
+            // The trait's innermost declaration, if the name is one.
+            let depth = self
+                .scopes
+                .iter()
+                .rposition(|scope| scope.contains_key(trait_name))
+                .map_or(0, |i| i + 1);
+            let Some(methods) = self.traits[depth].get(trait_name) else {
+                continue;
+            };
+            for &method in methods {
+                if stmt.methods.iter().any(|m| m.name.lexeme == method) {
+                    continue;
+                }
+                if let Some(other) = providers.insert(method, trait_name) {
+                    self.error(
+                        &trait_.name,
+                        &format!(
+                            "Method '{method}' is provided by both '{other}' and '{trait_name}'."
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn visit_enum_stmt(&mut self, stmt: &'a stmt::Enum) -> Result<()> {
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        let mut seen = HashSet::new();
+        for variant in &stmt.variants {
+            if variant.lexeme == "values" {
+                self.error(variant, "Can't name an enum variant 'values'.");
+            } else if !seen.insert(variant.lexeme.as_str()) {
+                self.error(
+                    variant,
+                    &format!("Variant '{}' is declared more than once.", variant.lexeme),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     fn visit_expression_stmt(&mut self, stmt: &stmt::Expression) -> Result<()> {
         self.resolve_expr(&stmt.expression)?;
         Ok(())
     }
 
+    fn visit_for_in_stmt(&mut self, stmt: &'a stmt::ForIn) -> Result<()> {
+        self.resolve_expr(&stmt.iterable)?;
+
+        // The loop variable gets a fresh scope on each iteration.
+        self.begin_scope();
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        self.resolve_stmt(&stmt.body)?;
+        self.end_scope();
+
+        Ok(())
+    }
+
     fn visit_function_stmt(&mut self, stmt: &'a stmt::Function) -> Result<()> {
         self.declare(&stmt.name);
         self.define(&stmt.name);
@@ -124,6 +215,41 @@ where
         Ok(())
     }
 
+    fn visit_match_stmt(&mut self, stmt: &'a stmt::Match) -> Result<()> {
+        self.resolve_expr(&stmt.value)?;
+
+        for arm in &stmt.arms {
+            // Each arm gets its own scope for the variables its pattern binds.
+            self.begin_scope();
+            self.resolve_pattern(&arm.pattern)?;
+            if let Some(guard) = &arm.guard {
+                self.resolve_expr(guard)?;
+            }
+            self.resolve_stmt(&arm.body)?;
+            self.end_scope();
+        }
+
+        Ok(())
+    }
+
+    fn resolve_pattern(&mut self, pattern: &'a stmt::Pattern) -> Result<()> {
+        match pattern {
+            Pattern::Wildcard | Pattern::Literal(_) => {}
+            Pattern::Binding(name) => {
+                self.declare(name);
+                self.define(name);
+            }
+            Pattern::Class(class, fields) => {
+                self.visit_variable_expr(class)?;
+                for (_, pattern) in fields {
+                    self.resolve_pattern(pattern)?;
+                }
+            }
+            Pattern::Value(value) => self.resolve_expr(value)?,
+        }
+        Ok(())
+    }
+
     fn visit_print_stmt(&mut self, stmt: &stmt::Print) -> Result<()> {
         self.resolve_expr(&stmt.expression)?;
         Ok(())
@@ -137,6 +263,8 @@ where
         if let Some(value) = &stmt.value {
             if self.current_function == FunctionType::Initializer {
                 self.error(&stmt.keyword, "Can't return a value from an initializer.");
+            } else if self.in_generator {
+                self.error(&stmt.keyword, "Can't return a value from a generator.");
             }
 
             self.resolve_expr(value)?;
@@ -145,12 +273,48 @@ where
         Ok(())
     }
 
+    fn visit_trait_stmt(&mut self, stmt: &'a stmt::Trait) -> Result<()> {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Trait;
+
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        self.traits.last_mut().unwrap().insert(
+            &stmt.name.lexeme,
+            stmt.methods
+                .iter()
+                .map(|m| m.name.lexeme.as_str())
+                .collect(),
+        );
+
+        self.begin_scope();
+        self.scopes.last_mut().unwrap().insert("this", true);
+
+        for method in &stmt.methods {
+            if method.name.lexeme == "init" {
+                self.error(&method.name, "Can't have an initializer in a trait.");
+            }
+            self.resolve_function(method, FunctionType::Method)?;
+        }
+
+        self.end_scope();
+
+        self.current_class = enclosing_class;
+
+        Ok(())
+    }
+
     fn visit_var_stmt(&mut self, stmt: &'a stmt::Var) -> Result<()> {
         self.declare(&stmt.name);
         if let Some(initializer) = &stmt.initializer {
             self.resolve_expr(initializer)?
         }
         self.define(&stmt.name);
+        if stmt.constant {
+            if let Some(constants) = self.constants.last_mut() {
+                constants.insert(&stmt.name.lexeme);
+            }
+        }
 
         Ok(())
     }
@@ -161,8 +325,41 @@ where
         Ok(())
     }
 
+    fn visit_yield_stmt(&mut self, stmt: &'a stmt::Yield) -> Result<()> {
+        match self.current_function {
+            FunctionType::None => {
+                self.error(&stmt.keyword, "Can't yield from top-level code.");
+            }
+            FunctionType::Initializer => {
+                self.error(&stmt.keyword, "Can't yield from an initializer.");
+            }
+            _ => {}
+        }
+
+        if let Some(value) = &stmt.value {
+            self.resolve_expr(value)?;
+        }
+
+        Ok(())
+    }
+
     fn visit_assign_expr(&mut self, expr: &expr::Assign) -> Result<()> {
         self.resolve_expr(&expr.value)?;
+
+        // Assignments to global constants are caught at runtime instead.
+        let declaring_scope = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(expr.name.lexeme.as_str()));
+        if let Some(i) = declaring_scope {
+            if self.constants[i].contains(expr.name.lexeme.as_str()) {
+                self.error(
+                    &expr.name,
+                    &format!("Can't assign to constant '{}'.", expr.name.lexeme),
+                );
+            }
+        }
+
         self.resolve_local(expr.id(), &expr.name)?;
         Ok(())
     }
@@ -180,6 +377,10 @@ where
             self.resolve_expr(argument)?;
         }
 
+        for (_, argument) in &expr.named_arguments {
+            self.resolve_expr(argument)?;
+        }
+
         Ok(())
     }
 
@@ -212,6 +413,8 @@ where
     fn visit_super_expr(&mut self, expr: &expr::Super) -> Result<()> {
         if self.current_class == ClassType::None {
             self.error(&expr.keyword, "Can't use 'super' outside of a class.");
+        } else if self.current_class == ClassType::Trait {
+            self.error(&expr.keyword, "Can't use 'super' in a trait.");
         } else if self.current_class != ClassType::SubClass {
             self.error(
                 &expr.keyword,
@@ -242,7 +445,7 @@ where
         if self
             .scopes
             .last()
-            .map_or(false, |s| s.get(&expr.name.lexeme.as_str()) == Some(&false))
+            .is_some_and(|s| s.get(&expr.name.lexeme.as_str()) == Some(&false))
         {
             self.error(
                 &expr.name,
@@ -269,13 +472,18 @@ where
         match statement {
             Stmt::Block(s) => self.visit_block_stmt(s),
             Stmt::Class(s) => self.visit_class_stmt(s),
+            Stmt::Enum(s) => self.visit_enum_stmt(s),
             Stmt::Expression(s) => self.visit_expression_stmt(s),
+            Stmt::ForIn(s) => self.visit_for_in_stmt(s),
             Stmt::Function(s) => self.visit_function_stmt(s),
             Stmt::If(s) => self.visit_if_stmt(s),
+            Stmt::Match(s) => self.visit_match_stmt(s),
             Stmt::Print(s) => self.visit_print_stmt(s),
             Stmt::Return(s) => self.visit_return_stmt(s),
+            Stmt::Trait(s) => self.visit_trait_stmt(s),
             Stmt::Var(s) => self.visit_var_stmt(s),
             Stmt::While(s) => self.visit_while_stmt(s),
+            Stmt::Yield(s) => self.visit_yield_stmt(s),
         }
     }
 
@@ -303,28 +511,46 @@ where
     ) -> Result<()> {
         let enclosing_function = self.current_function;
         self.current_function = type_;
+        let enclosing_generator = self.in_generator;
+        self.in_generator = function.is_generator;
 
         self.begin_scope();
         for param in &function.params {
-            self.declare(param);
-            self.define(param);
+            // Defaults are evaluated in the function's scope, so they can refer
+            // to the parameters before them.
+            if let Some(default) = &param.default {
+                self.resolve_expr(default)?;
+            }
+            self.declare(&param.name);
+            self.define(&param.name);
+        }
+        if let Some(rest) = &function.rest {
+            self.declare(rest);
+            self.define(rest);
         }
         self.resolve_stmts(&function.body)?;
         self.end_scope();
         self.current_function = enclosing_function;
+        self.in_generator = enclosing_generator;
 
         Ok(())
     }
 
     fn begin_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.constants.push(HashSet::new());
+        self.traits.push(HashMap::new());
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop().expect("Scope stack underflow.");
+        self.constants.pop();
+        self.traits.pop();
     }
 
     fn declare(&mut self, name: &'a Token) {
+        // Whatever is declared replaces any trait of the same name.
+        self.traits.last_mut().unwrap().remove(name.lexeme.as_str());
         if let Some(scope) = self.scopes.last() {
             if scope.contains_key(name.lexeme.as_str()) {
                 self.error(name, "Already a variable with this name in this scope.");
@@ -360,7 +586,7 @@ mod test {
     use crate::parser::Parser;
     use crate::scanner::Scanner;
 
-    use gc::{Gc, GcCell};
+    use std::sync::{Arc, Mutex};
 
     fn resolver_test(
         source: &str,
@@ -381,7 +607,7 @@ mod test {
         // Resolver tests should always parse.
         assert_eq!(error_count, 0);
 
-        let output = Gc::new(GcCell::new(Vec::new()));
+        let output = Arc::new(Mutex::new(Vec::new()));
         let mut interpreter = Interpreter::new(InterpreterOutput::ByteVec(output));
 
         Resolver::new(&mut interpreter, |_, err| {
@@ -408,4 +634,120 @@ mod test {
         let expected_error_message = Some("Can't use 'this' outside of a class.");
         resolver_test(source, 1, expected_error_message)
     }
+
+    #[test]
+    fn assign_to_local_constant() -> Result<()> {
+        let source = r"
+            {
+                const a = 1;
+                fun f() {
+                    a = 2;
+                }
+            }
+        ";
+        let expected_error_message = Some("Can't assign to constant 'a'.");
+        resolver_test(source, 1, expected_error_message)
+    }
+
+    #[test]
+    fn trait_method_conflict() -> Result<()> {
+        let source = r"
+            trait A { f() {} g() {} }
+            trait B { f() {} }
+            trait C { g() {} }
+            class Ok with A, B { f() {} }
+            class NotOk with A, B, C { f() {} }
+        ";
+        let expected_error_message = Some("Method 'g' is provided by both 'A' and 'C'.");
+        resolver_test(source, 1, expected_error_message)
+    }
+
+    #[test]
+    fn trait_method_conflict_after_shadowing() -> Result<()> {
+        let source = r"
+            trait A { f() {} }
+            trait B { f() {} }
+            {
+                trait A { g() {} }
+                class Ok with A, B {}
+            }
+            class NotOk with A, B {}
+            {
+                var A = 1;
+                class AlsoOk with A, B {}
+            }
+        ";
+        let expected_error_message = Some("Method 'f' is provided by both 'A' and 'B'.");
+        resolver_test(source, 1, expected_error_message)
+    }
+
+    #[test]
+    fn super_in_trait() -> Result<()> {
+        let source = r"
+            trait A {
+                f() { super.f(); }
+            }
+        ";
+        let expected_error_message = Some("Can't use 'super' in a trait.");
+        resolver_test(source, 1, expected_error_message)
+    }
+
+    #[test]
+    fn initializer_in_trait() -> Result<()> {
+        let source = r"
+            trait A {
+                init() {}
+            }
+        ";
+        let expected_error_message = Some("Can't have an initializer in a trait.");
+        resolver_test(source, 1, expected_error_message)
+    }
+
+    #[test]
+    fn duplicate_enum_variant() -> Result<()> {
+        let source = r"
+            enum Color { Red, Green, Red }
+        ";
+        let expected_error_message = Some("Variant 'Red' is declared more than once.");
+        resolver_test(source, 1, expected_error_message)
+    }
+
+    #[test]
+    fn yield_outside_function() -> Result<()> {
+        let source = r"
+            yield 1;
+        ";
+        let expected_error_message = Some("Can't yield from top-level code.");
+        resolver_test(source, 1, expected_error_message)
+    }
+
+    #[test]
+    fn return_value_from_generator() -> Result<()> {
+        let source = r"
+            fun g() {
+                yield 1;
+                return 2;
+            }
+        ";
+        let expected_error_message = Some("Can't return a value from a generator.");
+        resolver_test(source, 1, expected_error_message)
+    }
+
+    #[test]
+    fn match_bindings_are_scoped_to_arm() -> Result<()> {
+        let source = r"
+            fun f(value) {
+                match (value) {
+                    x => print x;
+                }
+                var x = 1;
+                match (value) {
+                    Foo(a, b: a) => print a;
+                }
+            }
+        ";
+        let expected_error_message =
+            Some("Already a variable with this name in this scope.");
+        resolver_test(source, 1, expected_error_message)
+    }
 }
@@ -8,20 +8,28 @@ use phf::phf_map;
 static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "and" => TT::And,
     "class" => TT::Class,
+    "const" => TT::Const,
     "else" => TT::Else,
+    "enum" => TT::Enum,
     "false" => TT::False,
     "for" => TT::For,
     "fun" => TT::Fun,
     "if" => TT::If,
+    "in" => TT::In,
+    "is" => TT::Is,
+    "match" => TT::Match,
     "nil" => TT::Nil,
     "or" => TT::Or,
     "print" => TT::Print,
     "return" => TT::Return,
     "super" => TT::Super,
     "this" => TT::This,
+    "trait" => TT::Trait,
     "true" => TT::True,
     "var" => TT::Var,
-    "while" => TT::While
+    "while" => TT::While,
+    "with" => TT::With,
+    "yield" => TT::Yield
 };
 
 pub struct Scanner<F>
@@ -86,8 +94,17 @@ where
             b')' => self.add_token(TT::RightParen),
             b'{' => self.add_token(TT::LeftBrace),
             b'}' => self.add_token(TT::RightBrace),
+            b':' => self.add_token(TT::Colon),
             b',' => self.add_token(TT::Comma),
-            b'.' => self.add_token(TT::Dot),
+            b'.' => {
+                if self.peek() == b'.' && self.peek_next() == b'.' {
+                    self.advance();
+                    self.advance();
+                    self.add_token(TT::DotDotDot);
+                } else {
+                    self.add_token(TT::Dot);
+                }
+            }
             b'-' => self.add_token(TT::Minus),
             b'+' => self.add_token(TT::Plus),
             b';' => self.add_token(TT::Semicolon),
@@ -97,8 +114,14 @@ where
                 self.add_token(if m { TT::BangEqual } else { TT::Bang })
             }
             b'=' => {
-                let m = self.match_(b'=');
-                self.add_token(if m { TT::EqualEqual } else { TT::Equal })
+                let type_ = if self.match_(b'=') {
+                    TT::EqualEqual
+                } else if self.match_(b'>') {
+                    TT::FatArrow
+                } else {
+                    TT::Equal
+                };
+                self.add_token(type_)
             }
             b'<' => {
                 let m = self.match_(b'=');
@@ -110,9 +133,16 @@ where
             }
             b'/' => {
                 if self.match_(b'/') {
+                    // As in Rust, `////` starts an ordinary comment.
+                    let is_doc = self.peek() == b'/' && self.peek_next() != b'/';
                     while self.peek() != b'\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    if is_doc {
+                        self.doc_comment();
+                    }
+                } else if self.match_(b'*') {
+                    self.block_comment();
                 } else {
                     self.add_token(TT::Slash);
                 }
@@ -126,6 +156,31 @@ where
         }
     }
 
+    /// A `///` comment, whose text is kept without the slashes and a leading
+    /// space.
+    fn doc_comment(&mut self) {
+        let text = &self.source[self.start + 3..self.current];
+        let text = text.strip_prefix(' ').unwrap_or(text).trim_end().to_owned();
+        self.add_token_literal(TT::DocComment, Object::String(text.into()));
+    }
+
+    /// A `/* ... */` comment, which may contain other block comments.
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error("Unterminated block comment.");
+                return;
+            }
+            match self.advance() {
+                b'/' if self.match_(b'*') => depth += 1,
+                b'*' if self.match_(b'/') => depth -= 1,
+                b'\n' => self.line += 1,
+                _ => {}
+            }
+        }
+    }
+
     fn identifier(&mut self) {
         while is_alpha_numeric(self.peek()) {
             self.advance();
@@ -137,25 +192,70 @@ where
     }
 
     fn number(&mut self) {
-        while is_digit(self.peek()) {
-            self.advance();
+        if self.source.as_bytes()[self.start] == b'0' {
+            let radix = match self.peek() {
+                b'b' | b'B' => Some(2),
+                b'o' | b'O' => Some(8),
+                b'x' | b'X' => Some(16),
+                _ => None,
+            };
+            if let Some(radix) = radix {
+                self.advance();
+                return self.radix_number(radix);
+            }
         }
 
+        self.digits(is_digit);
+        let mut is_float = false;
+
         if self.peek() == b'.' && is_digit(self.peek_next()) {
             self.advance();
+            self.digits(is_digit);
+            is_float = true;
+        }
 
-            while is_digit(self.peek()) {
-                self.advance();
+        if matches!(self.peek(), b'e' | b'E') {
+            let sign = matches!(self.peek_next(), b'+' | b'-') as usize;
+            if is_digit(self.peek_at(1 + sign)) {
+                for _ in 0..=sign {
+                    self.advance();
+                }
+                self.digits(is_digit);
+                is_float = true;
             }
         }
-        self.add_token_literal(
-            TT::Number,
-            Object::Number(
-                self.source[self.start..self.current]
-                    .parse()
-                    .expect("BUG: failed to parse Number."),
-            ),
-        );
+
+        let text = self.source[self.start..self.current].replace('_', "");
+        // Literals without a decimal point or exponent are integers, unless
+        // they're too big for one.
+        let literal = match text.parse() {
+            Ok(integer) if !is_float => Object::Integer(integer),
+            _ => Object::Number(text.parse().expect("BUG: failed to parse Number.")),
+        };
+        self.add_token_literal(TT::Number, literal);
+    }
+
+    /// Scan an integer written in binary, octal or hex, after its prefix.
+    fn radix_number(&mut self, radix: u32) {
+        let digits_start = self.current;
+        self.digits(|c| (c as char).is_digit(radix));
+
+        let digits = self.source[digits_start..self.current].replace('_', "");
+        if digits.is_empty() {
+            self.error("Expect digits after number prefix.");
+            return;
+        }
+        match i64::from_str_radix(&digits, radix) {
+            Ok(integer) => self.add_token_literal(TT::Number, Object::Integer(integer)),
+            Err(_) => self.error("Integer literal is too large."),
+        }
+    }
+
+    /// Consume digits, which may be separated by single underscores.
+    fn digits(&mut self, is_digit: impl Fn(u8) -> bool) {
+        while is_digit(self.peek()) || (self.peek() == b'_' && is_digit(self.peek_next())) {
+            self.advance();
+        }
     }
 
     fn string(&mut self) {
@@ -193,14 +293,18 @@ where
         if self.is_at_end() {
             return b'\0';
         }
-        return self.source.as_bytes()[self.current];
+        self.source.as_bytes()[self.current]
     }
 
     fn peek_next(&self) -> u8 {
-        if self.current + 1 >= self.source.len() {
+        self.peek_at(1)
+    }
+
+    fn peek_at(&self, offset: usize) -> u8 {
+        if self.current + offset >= self.source.len() {
             return b'\0';
         }
-        self.source.as_bytes()[self.current + 1]
+        self.source.as_bytes()[self.current + offset]
     }
 
     fn is_at_end(&self) -> bool {
@@ -209,7 +313,7 @@ where
 
     fn advance(&mut self) -> u8 {
         self.current += 1;
-        return self.source.as_bytes()[self.current - 1];
+        self.source.as_bytes()[self.current - 1]
     }
 
     fn add_token(&mut self, type_: TokenType) {
@@ -250,7 +354,7 @@ mod test {
                 Token::new(TT::Var, "var", Object::Nil, 1).into(),
                 Token::new(TT::Identifier, "a", Object::Nil, 1).into(),
                 Token::new(TT::Equal, "=", Object::Nil, 1).into(),
-                Token::new(TT::Number, "1", Object::Number(1.0), 1).into(),
+                Token::new(TT::Number, "1", Object::Integer(1), 1).into(),
                 Token::new(TT::Semicolon, ";", Object::Nil, 1).into(),
                 Token::new(TT::Var, "var", Object::Nil, 1).into(),
                 Token::new(TT::Identifier, "b", Object::Nil, 1).into(),
@@ -276,6 +380,56 @@ mod test {
         );
     }
 
+    #[test]
+    fn numbers() {
+        let source = "0xFF 0b1010 0o17 1_000_000 1e-9 2.5E3 1e 0x";
+        let mut errors = Vec::new();
+        let tokens =
+            Scanner::new(source, |_, message| errors.push(message.to_owned())).scan_tokens();
+        let literals: Vec<_> = tokens.iter().map(|t| t.literal.clone()).collect();
+        assert_eq!(
+            literals,
+            vec![
+                Object::Integer(255),
+                Object::Integer(10),
+                Object::Integer(15),
+                Object::Integer(1_000_000),
+                Object::Number(1e-9),
+                Object::Number(2500.0),
+                Object::Integer(1),
+                Object::Nil,
+                Object::Nil,
+            ]
+        );
+        assert!(matches!(tokens[5].literal, Object::Number(_)));
+        assert_eq!(errors, vec!["Expect digits after number prefix."]);
+    }
+
+    #[test]
+    fn comments() {
+        let source = "/* a /* nested\n */ comment */ 1\n/// Docs.\n//// Not docs.\n/* 2";
+        let mut errors = Vec::new();
+        let tokens = Scanner::new(source, |line, message| {
+            errors.push(format!("{line}: {message}"))
+        })
+        .scan_tokens();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(TT::Number, "1", Object::Integer(1), 2).into(),
+                Token::new(
+                    TT::DocComment,
+                    "/// Docs.",
+                    Object::String("Docs.".to_owned().into()),
+                    3
+                )
+                .into(),
+                Token::new(TT::Eof, "", Object::Nil, 5).into(),
+            ]
+        );
+        assert_eq!(errors, vec!["5: Unterminated block comment."]);
+    }
+
     #[test]
     fn unclosed_string() {
         let mut error_count = 0usize;
@@ -0,0 +1,183 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_native::{define_native, NativeCall};
+use crate::lox_result::Result;
+use crate::object::Object;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+
+use corosensei::stack::DefaultStack;
+use corosensei::{Coroutine, CoroutineResult, Yielder};
+use gc::Gc;
+
+type FiberCoroutine = Coroutine<*mut Interpreter, (), Result<()>>;
+type FiberYielder = Yielder<*mut Interpreter, ()>;
+
+/// The id the main program waits under. It isn't a fiber, but it can block on
+/// a channel like one.
+pub const MAIN_FIBER: usize = 0;
+
+// Stacks are allocated lazily by the OS, so this only limits how deep a fiber
+// can recurse.
+const FIBER_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Cooperatively schedules the fibers created with `spawn`.
+///
+/// Fibers run on their own stacks, and only the main program resumes them: it
+/// runs ready fibers in the order they became ready whenever it blocks, and
+/// once the script has finished. A fiber runs until it blocks or finishes,
+/// then control returns to the main program. This keeps the order fibers run
+/// in, and so the order of their output, deterministic.
+#[derive(Default)]
+pub struct Scheduler {
+    // Fibers which have been created but aren't running.
+    fibers: HashMap<usize, FiberCoroutine>,
+    // The yielder of each fiber which has started, used to suspend it.
+    yielders: HashMap<usize, *const FiberYielder>,
+    // Where each blocked fiber is waiting, for reporting deadlocks.
+    waiting: BTreeMap<usize, Gc<Token>>,
+    ready: VecDeque<usize>,
+    current: usize,
+    main_ready: bool,
+    next_id: usize,
+}
+
+impl Scheduler {
+    /// The id of the fiber that's running, or `MAIN_FIBER`.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Make a fiber which is waiting ready to run again.
+    pub fn wake(&mut self, id: usize) {
+        if id == MAIN_FIBER {
+            self.main_ready = true;
+        } else if self.waiting.remove(&id).is_some() {
+            self.ready.push_back(id);
+        }
+    }
+}
+
+pub fn define_globals(globals: &Environment) {
+    define_native(globals, "spawn", Arity::at_least(1), spawn);
+}
+
+/// `spawn(fn, args...)` creates a fiber which calls `fn` with `args`.
+fn spawn(interpreter: &mut Interpreter, call: &NativeCall) -> Result<Object> {
+    let callable = match &call.arguments[0] {
+        Object::Callable(callable) => callable.clone(),
+        _ => return Err(call.error("Argument 1 to 'spawn' must be a function.")),
+    };
+    let arguments = call.arguments[1..].to_vec();
+    let paren = call.paren.clone();
+
+    let scheduler = interpreter.scheduler();
+    scheduler.next_id += 1;
+    let id = scheduler.next_id;
+
+    let stack = DefaultStack::new(FIBER_STACK_SIZE)?;
+    let coroutine = Coroutine::with_stack(
+        stack,
+        move |yielder: &FiberYielder, interpreter: *mut Interpreter| {
+            // SAFETY: fibers are only resumed by `run_fiber`, which passes the
+            // interpreter it's borrowing mutably and doesn't use it until the
+            // fiber suspends or finishes. No fiber outlives a call to
+            // `Interpreter::interpret`, so the interpreter can't move while
+            // the fiber is suspended.
+            let interpreter = unsafe { &mut *interpreter };
+            interpreter.scheduler().yielders.insert(id, yielder);
+            let result = run_callable(interpreter, &callable, &paren, &arguments);
+            interpreter.scheduler().yielders.remove(&id);
+            result
+        },
+    );
+
+    scheduler.fibers.insert(id, coroutine);
+    scheduler.ready.push_back(id);
+    Ok(Object::Nil)
+}
+
+fn run_callable(
+    interpreter: &mut Interpreter,
+    callable: &LoxCallable,
+    paren: &Gc<Token>,
+    arguments: &[Object],
+) -> Result<()> {
+    callable.call(interpreter, paren, arguments, &[])?;
+    Ok(())
+}
+
+/// Block the current fiber until something calls `Scheduler::wake` with its
+/// id. The main program blocks by running other fibers, and reports a deadlock
+/// if none of them are ready.
+pub fn wait(interpreter: &mut Interpreter, token: &Gc<Token>) -> Result<()> {
+    let current = interpreter.scheduler().current;
+
+    if current != MAIN_FIBER {
+        let scheduler = interpreter.scheduler();
+        scheduler.waiting.insert(current, token.clone());
+        let yielder = scheduler.yielders[&current];
+        // The environment is shared with whatever runs while this fiber is
+        // suspended, so restore it once the fiber is resumed.
+        let environment = interpreter.environment();
+        interpreter.in_environment(environment, |_| {
+            // SAFETY: the yielder lives on the stack of the fiber that's
+            // running, which this is.
+            unsafe { (*yielder).suspend(()) };
+            Ok(())
+        })?;
+        return Ok(());
+    }
+
+    interpreter.scheduler().main_ready = false;
+    while !interpreter.scheduler().main_ready {
+        match interpreter.scheduler().ready.pop_front() {
+            Some(id) => run_fiber(interpreter, id)?,
+            None => return Err(deadlock(token)),
+        }
+    }
+    Ok(())
+}
+
+/// Run fibers until none are ready, once the main program has finished.
+pub fn run_remaining_fibers(interpreter: &mut Interpreter) -> Result<()> {
+    while let Some(id) = interpreter.scheduler().ready.pop_front() {
+        run_fiber(interpreter, id)?;
+    }
+    match interpreter.scheduler().waiting.values().next() {
+        Some(token) => Err(deadlock(token)),
+        None => Ok(()),
+    }
+}
+
+/// Resume a fiber until it blocks or finishes. Errors in the fiber propagate to
+/// the main program.
+fn run_fiber(interpreter: &mut Interpreter, id: usize) -> Result<()> {
+    let mut coroutine = interpreter
+        .scheduler()
+        .fibers
+        .remove(&id)
+        .expect("Expect ready fibers to exist.");
+
+    interpreter.scheduler().current = id;
+    let environment = interpreter.environment();
+    let result = interpreter
+        .in_environment(environment, |interpreter| Ok(coroutine.resume(interpreter)));
+    interpreter.scheduler().current = MAIN_FIBER;
+
+    match result? {
+        CoroutineResult::Yield(()) => {
+            interpreter.scheduler().fibers.insert(id, coroutine);
+            Ok(())
+        }
+        CoroutineResult::Return(result) => result,
+    }
+}
+
+fn deadlock(token: &Gc<Token>) -> Box<dyn Error> {
+    RuntimeError::new(token.clone(), "Deadlock: every fiber is blocked.").into()
+}
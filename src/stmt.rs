@@ -1,4 +1,5 @@
 use crate::expr::{Expr, Variable};
+use crate::object::Object;
 use crate::token::Token;
 use crate::unique_id::unique_usize;
 
@@ -12,19 +13,42 @@ crate::ast_struct!(
     Gc<Token>,
     superclass,
     Option<Gc<Variable>>,
+    traits,
+    Vec<Gc<Variable>>,
     methods,
-    Vec<Gc<Function>>
+    Vec<Gc<Function>>,
+    doc,
+    Option<String>
 );
+crate::ast_struct!(Stmt, Enum, name, Gc<Token>, variants, Vec<Gc<Token>>);
 crate::ast_struct!(Stmt, Expression, expression, Expr);
+crate::ast_struct!(
+    Stmt,
+    ForIn,
+    keyword,
+    Gc<Token>,
+    name,
+    Gc<Token>,
+    iterable,
+    Expr,
+    body,
+    Stmt
+);
 crate::ast_struct!(
     Stmt,
     Function,
     name,
     Gc<Token>,
     params,
-    Vec<Gc<Token>>,
+    Vec<Parameter>,
+    rest,
+    Option<Gc<Token>>,
     body,
-    Vec<Stmt>
+    Vec<Stmt>,
+    is_generator,
+    bool,
+    doc,
+    Option<String>
 );
 crate::ast_struct!(
     Stmt,
@@ -36,9 +60,86 @@ crate::ast_struct!(
     else_branch,
     Option<Stmt>
 );
-crate::ast_struct!(Stmt, Print, expression, Expr);
+crate::ast_struct!(
+    Stmt,
+    Match,
+    keyword,
+    Gc<Token>,
+    value,
+    Expr,
+    arms,
+    Vec<MatchArm>
+);
+crate::ast_struct!(Stmt, Print, keyword, Gc<Token>, expression, Expr);
 crate::ast_struct!(Stmt, Return, keyword, Gc<Token>, value, Option<Expr>);
-crate::ast_struct!(Stmt, Var, name, Gc<Token>, initializer, Option<Expr>);
+crate::ast_struct!(
+    Stmt,
+    Var,
+    name,
+    Gc<Token>,
+    initializer,
+    Option<Expr>,
+    constant,
+    bool
+);
+crate::ast_struct!(Stmt, Trait, name, Gc<Token>, methods, Vec<Gc<Function>>);
 crate::ast_struct!(Stmt, While, condition, Expr, body, Stmt);
+crate::ast_struct!(Stmt, Yield, keyword, Gc<Token>, value, Option<Expr>);
+
+/// A named function parameter with an optional default value, which is
+/// evaluated at call time if no argument is passed for the parameter.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct Parameter {
+    pub name: Gc<Token>,
+    pub default: Option<Expr>,
+}
+
+/// One arm of a `match` statement. The body is executed if the pattern
+/// matches and the guard, if any, is truthy.
+#[derive(Clone, Debug, Finalize, Trace)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Stmt,
+}
+
+#[derive(Clone, Debug, Finalize, Trace)]
+pub enum Pattern {
+    /// `_`, which matches anything.
+    Wildcard,
+    /// A literal number, string, boolean or nil, which matches equal values.
+    Literal(Object),
+    /// A name, which matches anything and binds it to the name.
+    Binding(Gc<Token>),
+    /// `Point(x, y: 0)`, which matches instances of `Point` or its subclasses
+    /// whose fields match the given patterns. A bare field name binds the
+    /// field to a variable of the same name.
+    Class(Gc<Variable>, Vec<(Gc<Token>, Pattern)>),
+    /// A dotted name such as `Color.Red`, which matches values equal to the
+    /// one it evaluates to.
+    Value(Expr),
+}
+
+crate::ast_enum!(
+    Stmt, Block, Class, Enum, Expression, ForIn, Function, If, Match, Print, Return, Trait,
+    Var, While, Yield
+);
 
-crate::ast_enum!(Stmt, Block, Class, Expression, Function, If, Print, Return, Var, While);
+impl Stmt {
+    /// Whether the statement contains a `yield`, not counting any in nested
+    /// function declarations.
+    pub fn contains_yield(&self) -> bool {
+        match self {
+            Stmt::Block(s) => s.statements.iter().any(Stmt::contains_yield),
+            Stmt::ForIn(s) => s.body.contains_yield(),
+            Stmt::If(s) => {
+                s.then_branch.contains_yield()
+                    || s.else_branch.as_ref().is_some_and(Stmt::contains_yield)
+            }
+            Stmt::Match(s) => s.arms.iter().any(|arm| arm.body.contains_yield()),
+            Stmt::While(s) => s.body.contains_yield(),
+            Stmt::Yield(_) => true,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use crate::expr;
+use crate::interpreter::{Interpreter, InterpreterOptions, InterpreterOutput};
+use crate::lox_result::Result;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+use crate::stmt::{self, Stmt};
+use crate::token::Token;
+use crate::token_type::TokenType;
+
+use std::fs;
+
+use gc::Gc;
+
+/// How one test function went. `failure` says where and why it failed.
+#[derive(Debug, PartialEq)]
+pub struct TestResult {
+    pub name: String,
+    pub failure: Option<String>,
+}
+
+/// Run the tests in each file, printing a line for each test and a summary.
+/// Returns whether every test passed.
+pub fn run_tests(paths: &[String], options: &InterpreterOptions) -> bool {
+    let mut passed = 0;
+    let mut failed = 0;
+    for path in paths {
+        let results = match run_file(path, options) {
+            Ok(results) => results,
+            Err(error) => {
+                println!("FAIL {path}\n    {error}");
+                failed += 1;
+                continue;
+            }
+        };
+        for TestResult { name, failure } in results {
+            match failure {
+                None => {
+                    println!("PASS {path}: {name}");
+                    passed += 1;
+                }
+                Some(failure) => {
+                    println!("FAIL {path}: {name}\n    {failure}");
+                    failed += 1;
+                }
+            }
+        }
+    }
+    println!("\n{passed} passed, {failed} failed.");
+    failed == 0
+}
+
+/// Run every top-level function in the file whose name starts with `test_`, in
+/// the order they're declared. Each test gets a fresh interpreter, which runs
+/// the file's top level and then calls the test function, so tests can't
+/// affect each other. Fails if the file can't be read or has errors.
+pub fn run_file(path: &str, options: &InterpreterOptions) -> Result<Vec<TestResult>> {
+    let source = fs::read_to_string(path)?;
+    let statements = parse(&source)?;
+    let tests = statements.iter().filter_map(|statement| match statement {
+        Stmt::Function(function) if function.name.lexeme.starts_with("test_") => {
+            Some(function.name.clone())
+        }
+        _ => None,
+    });
+    let results = tests
+        .map(|name| TestResult {
+            name: name.lexeme.clone(),
+            failure: run_test(path, &statements, name, options),
+        })
+        .collect();
+    Ok(results)
+}
+
+fn parse(source: &str) -> Result<Vec<Stmt>> {
+    let mut errors = Vec::new();
+    let tokens = Scanner::new(source, |line, message| {
+        errors.push(format!("[line {line}] Error: {message}"))
+    })
+    .scan_tokens();
+    let statements = Parser::new(tokens, |token, message| {
+        errors.push(token_error(&token, message))
+    })
+    .parse()?;
+    if errors.is_empty() {
+        // Resolve the file once to report its errors before running any tests.
+        let mut interpreter = Interpreter::with_options(
+            InterpreterOutput::StdOut,
+            InterpreterOptions::default(),
+        );
+        Resolver::new(&mut interpreter, |token, message| {
+            errors.push(token_error(token, message))
+        })
+        .resolve(&statements)?;
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("\n    ").into());
+    }
+    Ok(statements)
+}
+
+fn token_error(token: &Token, message: &str) -> String {
+    if token.type_ == TokenType::Eof {
+        format!("[line {}] Error at end: {message}", token.line)
+    } else {
+        format!(
+            "[line {}] Error at '{}': {message}",
+            token.line, token.lexeme
+        )
+    }
+}
+
+fn run_test(
+    path: &str,
+    statements: &[Stmt],
+    name: Gc<Token>,
+    options: &InterpreterOptions,
+) -> Option<String> {
+    let mut statements = statements.to_vec();
+    statements.push(stmt::Expression::make(expr::Call::make(
+        expr::Variable::make(name.clone()),
+        name,
+        Vec::new(),
+        Vec::new(),
+    )));
+
+    let mut interpreter =
+        Interpreter::with_options(InterpreterOutput::StdOut, options.clone());
+    Resolver::new(&mut interpreter, |_, _| {})
+        .resolve(&statements)
+        .expect("Unexpected variable resolution error.");
+    let mut failure = None;
+    let exit = interpreter.interpret(&statements, |error| {
+        failure = Some(format!("{path}:{}: {}", error.token.line, error.message))
+    });
+    match exit {
+        // Exiting, even successfully, means the test didn't run to the end.
+        Some(exit) => Some(format!(
+            "{path}:{}: Exited with status {} before the test finished.",
+            exit.paren.line, exit.code
+        )),
+        None => failure,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn running_tests() -> Result<()> {
+        let source = r#"
+            var counter = 0;
+
+            fun test_passes() {
+                counter = counter + 1;
+                assertEqual(counter, 1);
+            }
+
+            fun test_is_isolated() {
+                counter = counter + 1;
+                assertEqual(counter, 1, "Counter");
+            }
+
+            fun test_fails() {
+                assertEqual(List("1"), List(1));
+            }
+
+            fun test_throws() {
+                fun bad() { return nil + 1; }
+                assertEqual(assertThrows(bad), "Operands must be two numbers or two strings.");
+                assert(false, "expected");
+            }
+
+            class P {
+                init(x) { this.x = x; }
+                equals(other) { return this.x == other.x; }
+            }
+
+            fun test_equality() {
+                assertEqual(P(1), P(1));
+                assertEqual(NAN, NAN);
+            }
+
+            fun test_exits() {
+                exit(0);
+                assert(false, "never");
+            }
+
+            fun helper() {}
+        "#;
+        let path = env::temp_dir().join(format!("lox-tw-tests-{}.lox", std::process::id()));
+        fs::write(&path, source)?;
+        let path = path.to_str().unwrap();
+        let results = run_file(path, &InterpreterOptions::default());
+        fs::remove_file(path)?;
+
+        let result = |name: &str, failure: Option<&str>| TestResult {
+            name: name.to_owned(),
+            failure: failure.map(str::to_owned),
+        };
+        assert_eq!(
+            results?,
+            vec![
+                result("test_passes", None),
+                result("test_is_isolated", None),
+                result(
+                    "test_fails",
+                    Some(&format!("{path}:15: Expected [1] but got [\"1\"]."))
+                ),
+                result(
+                    "test_throws",
+                    Some(&format!("{path}:21: Assertion failed: expected"))
+                ),
+                result("test_equality", None),
+                result(
+                    "test_exits",
+                    Some(&format!(
+                        "{path}:35: Exited with status 0 before the test finished."
+                    )),
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_files() {
+        let error = parse("fun test_x() { print; }").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at ';': Expect expression."
+        );
+        let error = parse("fun test_x() {}\nreturn 1;").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 2] Error at 'return': Can't return from top-level code."
+        );
+    }
+}
@@ -9,6 +9,7 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -22,11 +23,15 @@ pub enum TokenType {
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
 
+    // Three character tokens.
+    DotDotDot,
+
     // Literals.
     Identifier,
     String,
@@ -35,20 +40,31 @@ pub enum TokenType {
     // Keywords.
     And,
     Class,
+    Const,
     Else,
+    Enum,
     False,
     Fun,
     For,
     If,
+    In,
+    Is,
+    Match,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Trait,
     True,
     Var,
     While,
+    With,
+    Yield,
+
+    // Doc comments, which the parser attaches to declarations.
+    DocComment,
 
     Eof,
 }